@@ -15,7 +15,8 @@ import {
   HeartFilled,
   ClockCircleOutlined,
   EyeOutlined,
-  StarOutlined
+  StarOutlined,
+  WarningOutlined
 } from '@ant-design/icons';
 import { VideoCardProps } from '@/types';
 import { useAppStore } from '@/stores/appStore';
@@ -171,6 +172,15 @@ const VideoCard: React.FC<VideoCardProps> = ({
             {/* AI评分信息 */}
             <div className="score-section" style={{ marginTop: 12 }}>
               <Space direction="vertical" size={4} style={{ width: '100%' }}>
+                {/* 未经AI审核的提示 */}
+                {video.warning && (
+                  <Tooltip title={video.warning}>
+                    <Tag color="orange" icon={<WarningOutlined />}>
+                      未经审核
+                    </Tag>
+                  </Tooltip>
+                )}
+
                 {/* 总体评分 */}
                 {video.ai_score && (
                   <div className="score-item">
@@ -1,4 +1,4 @@
-import React, { useEffect, useState } from 'react';
+import React, { useEffect, useRef, useState } from 'react';
 import { 
   Card, 
   Button, 
@@ -25,7 +25,7 @@ import { useAppStore } from '@/stores/appStore';
 import { Video } from '@/types';
 import useI18n from '@/hooks/useI18n';
 import YouTubePlayer from '@/components/YouTubePlayer';
-// import api from '@/services/api'; // TODO: Uncomment when implementing getVideo API
+import api, { AppApiError, isTauriApp } from '@/services/api';
 
 const { Title, Text, Paragraph } = Typography;
 
@@ -36,6 +36,8 @@ const Player: React.FC = () => {
   const { addToFavorites, loading, searchResults } = useAppStore();
   const [video, setVideo] = useState<Video | null>(null);
   const [videoLoading, setVideoLoading] = useState(true);
+  const [playbackBlocked, setPlaybackBlocked] = useState<string | null>(null);
+  const sessionIdRef = useRef<number>();
 
   useEffect(() => {
     if (videoId) {
@@ -43,6 +45,74 @@ const Player: React.FC = () => {
     }
   }, [videoId]);
 
+  // 观看计时由后端负责：打开视频时开始会话，离开页面时结束
+  // 刷新页面会重新请求会话，超出每日时长或不在允许时段时后端会拒绝
+  useEffect(() => {
+    if (!video) return;
+    let cancelled = false;
+    setPlaybackBlocked(null);
+
+    api.startWatchSession(video.id)
+      .then(status => {
+        if (cancelled) {
+          if (status.session_id !== undefined) {
+            api.stopWatchSession(status.session_id).catch(error => console.error('Failed to stop watch session:', error));
+          }
+          return;
+        }
+        sessionIdRef.current = status.session_id;
+      })
+      .catch(error => {
+        if (cancelled) return;
+        if (error instanceof AppApiError && (error.code === 'ScreenTimeExceeded' || error.code === 'OutsideSchedule')) {
+          setPlaybackBlocked(error.detail);
+        } else {
+          console.error('Failed to start watch session:', error);
+        }
+      });
+
+    return () => {
+      cancelled = true;
+      const sessionId = sessionIdRef.current;
+      sessionIdRef.current = undefined;
+      if (sessionId !== undefined) {
+        api.stopWatchSession(sessionId).catch(error => console.error('Failed to stop watch session:', error));
+      }
+    };
+  }, [video?.id]);
+
+  // 播放过程中时长用完或离开允许时段时，后端结束会话并推送锁定事件
+  useEffect(() => {
+    if (!isTauriApp()) return;
+    let disposed = false;
+    let unlisteners: (() => void)[] = [];
+
+    import('@tauri-apps/api/event')
+      .then(({ listen }) => Promise.all([
+        listen('screen-time-lockout', () => {
+          sessionIdRef.current = undefined;
+          setPlaybackBlocked(t('player.screenTimeLockout', 'Screen time for today is used up'));
+        }),
+        listen('viewing-schedule-lockout', () => {
+          sessionIdRef.current = undefined;
+          setPlaybackBlocked(t('player.scheduleLockout', 'Videos are not allowed at this time'));
+        })
+      ]))
+      .then(fns => {
+        if (disposed) {
+          fns.forEach(unlisten => unlisten());
+        } else {
+          unlisteners = fns;
+        }
+      })
+      .catch(error => console.error('Failed to listen for lockout events:', error));
+
+    return () => {
+      disposed = true;
+      unlisteners.forEach(unlisten => unlisten());
+    };
+  }, []);
+
   const loadVideo = async (id: string) => {
     try {
       setVideoLoading(true);
@@ -188,20 +258,29 @@ const Player: React.FC = () => {
         {/* 视频播放区域 */}
         <Col xs={24} lg={16}>
           <Card className="video-player-card">
-            {/* YouTube视频播放器 */}
-            <YouTubePlayer
-              videoId={video.id}
-              autoplay={false}
-              onReady={() => {
-                console.log('Player ready for video:', video.id);
-              }}
-              onError={(error) => {
-                console.error('Player error:', error);
-              }}
-              onStateChange={(state) => {
-                console.log('Player state changed:', state);
-              }}
-            />
+            {/* YouTube视频播放器，被观看时长或时间表锁定时不显示 */}
+            {playbackBlocked ? (
+              <Alert
+                message={t('player.playbackBlocked', 'Playback is locked')}
+                description={playbackBlocked}
+                type="warning"
+                showIcon
+              />
+            ) : (
+              <YouTubePlayer
+                videoId={video.id}
+                autoplay={false}
+                onReady={() => {
+                  console.log('Player ready for video:', video.id);
+                }}
+                onError={(error) => {
+                  console.error('Player error:', error);
+                }}
+                onStateChange={(state) => {
+                  console.log('Player state changed:', state);
+                }}
+              />
+            )}
 
             {/* 视频标题和操作 */}
             <div style={{ marginTop: 16 }}>
@@ -33,7 +33,7 @@ import { useAppStore } from '@/stores/appStore'
 import { AppSettings, FilterPreset } from '@/types'
 import useI18n from '@/hooks/useI18n'
 import useTheme from '@/hooks/useTheme'
-import api from '@/services/api'
+import api, { AppApiError } from '@/services/api'
 
 const { Title, Paragraph, Text } = Typography
 const { TextArea } = Input
@@ -107,7 +107,7 @@ const Settings: React.FC = () => {
   const { t, changeLanguage, currentLanguage } = useI18n()
   const { theme, setTheme } = useTheme()
   const [form] = Form.useForm()
-  const { settings, loading, loadSettings, saveSettings } = useAppStore()
+  const { settings, loading, loadSettings, saveSettings, unlockParent, ensureParentUnlocked } = useAppStore()
   const [activeSection, setActiveSection] = useState('ai-config')
   const [showApiKey, setShowApiKey] = useState({
     openai: false,
@@ -152,8 +152,39 @@ const Settings: React.FC = () => {
     }
   }, [activeSection])
 
+  // 设置了家长PIN时，保存前需要先输入PIN解锁
+  const requestParentUnlock = (): Promise<boolean> => new Promise(resolve => {
+    let pin = ''
+    Modal.confirm({
+      title: t('settings.parentPinRequired', 'Parent PIN required'),
+      content: (
+        <Input.Password
+          autoFocus
+          placeholder={t('settings.parentPinPlaceholder', 'Enter the parent PIN')}
+          onChange={e => { pin = e.target.value }}
+        />
+      ),
+      okText: t('settings.parentUnlock', 'Unlock'),
+      onOk: async () => {
+        try {
+          await unlockParent(pin)
+          resolve(true)
+        } catch (error) {
+          // 输错时保持对话框打开，锁定时显示剩余时间
+          message.error(error instanceof AppApiError ? error.detail : t('settings.parentPinIncorrect', 'Incorrect parent PIN'))
+          throw error
+        }
+      },
+      onCancel: () => resolve(false)
+    })
+  })
+
   const handleSave = async (values: any) => {
     try {
+      if (!(await ensureParentUnlocked()) && !(await requestParentUnlock())) {
+        return
+      }
+
       // 合并现有设置和新值，确保不会丢失未修改的字段
       const appSettings: AppSettings = {
         ...settings, // 先保留所有现有设置
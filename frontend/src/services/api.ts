@@ -1,10 +1,12 @@
 // API抽象层 - 统一的API接口定义
 import type { ApiService } from './interfaces'
+import type { ProfileInput, ViewingSchedule, WatchEvent } from '@/types'
 import { TauriApiService } from './tauri'
 import { MockApiService } from './mock'
 
 // 重新导出接口供其他模块使用
 export type { ApiService }
+export { AppApiError } from './tauri'
 
 /**
  * API工厂 - 根据环境返回对应的API实现
@@ -177,21 +179,53 @@ export const api = {
   searchVideos: (request: any) => ApiFactory.getInstance().searchVideos(request),
   analyzeVideo: (request: any) => ApiFactory.getInstance().analyzeVideo(request),
   saveVideo: (video: any) => ApiFactory.getInstance().saveVideo(video),
-  deleteVideo: (videoId: string) => ApiFactory.getInstance().deleteVideo(videoId),
+  deleteVideo: (videoId: string, parentToken?: string) => ApiFactory.getInstance().deleteVideo(videoId, parentToken),
   getCachedVideos: () => ApiFactory.getInstance().getCachedVideos(),
 
   // 收藏相关方法
-  getFavorites: () => ApiFactory.getInstance().getFavorites(),
-  addToFavorites: (videoId: string, notes?: string) => ApiFactory.getInstance().addToFavorites(videoId, notes),
-  removeFromFavorites: (favoriteId: number) => ApiFactory.getInstance().removeFromFavorites(favoriteId),
+  getFavorites: (profileId?: number, parentToken?: string) => ApiFactory.getInstance().getFavorites(profileId, parentToken),
+  addToFavorites: (videoId: string, notes?: string, profileId?: number, parentToken?: string) => ApiFactory.getInstance().addToFavorites(videoId, notes, profileId, parentToken),
+  removeFromFavorites: (favoriteId: number, parentToken?: string) => ApiFactory.getInstance().removeFromFavorites(favoriteId, parentToken),
 
   // 设置相关方法
   getSettings: () => ApiFactory.getInstance().getSettings(),
-  saveSettings: (settings: any) => ApiFactory.getInstance().saveSettings(settings),
+  saveSettings: (settings: any, parentToken?: string) => ApiFactory.getInstance().saveSettings(settings, parentToken),
 
   // 缓存相关方法
-  clearCache: () => ApiFactory.getInstance().clearCache(),
-  getSearchHistory: (limit?: number) => ApiFactory.getInstance().getSearchHistory(limit),
+  clearCache: (parentToken?: string) => ApiFactory.getInstance().clearCache(parentToken),
+  getSearchHistory: (limit?: number, profileId?: number, parentToken?: string) => ApiFactory.getInstance().getSearchHistory(limit, profileId, parentToken),
+
+  // 家长PIN相关方法
+  getParentPinStatus: (parentToken?: string) => ApiFactory.getInstance().getParentPinStatus(parentToken),
+  setParentPin: (pin: string, parentToken?: string) => ApiFactory.getInstance().setParentPin(pin, parentToken),
+  unlockParent: (pin: string) => ApiFactory.getInstance().unlockParent(pin),
+  lockParent: () => ApiFactory.getInstance().lockParent(),
+
+  // 观看时长相关方法
+  startWatchSession: (videoId?: string, profileId?: number, parentToken?: string) => ApiFactory.getInstance().startWatchSession(videoId, profileId, parentToken),
+  stopWatchSession: (sessionId: number, parentToken?: string) => ApiFactory.getInstance().stopWatchSession(sessionId, parentToken),
+  getScreenTimeStatus: (profileId?: number) => ApiFactory.getInstance().getScreenTimeStatus(profileId),
+
+  // YouTube配额相关方法
+  getQuotaUsage: () => ApiFactory.getInstance().getQuotaUsage(),
+
+  // 儿童档案相关方法
+  listProfiles: () => ApiFactory.getInstance().listProfiles(),
+  getActiveProfile: () => ApiFactory.getInstance().getActiveProfile(),
+  createProfile: (profile: ProfileInput, parentToken?: string) => ApiFactory.getInstance().createProfile(profile, parentToken),
+  updateProfile: (profileId: number, profile: ProfileInput, parentToken?: string) => ApiFactory.getInstance().updateProfile(profileId, profile, parentToken),
+  deleteProfile: (profileId: number, parentToken?: string) => ApiFactory.getInstance().deleteProfile(profileId, parentToken),
+  switchProfile: (profileId: number, parentToken?: string) => ApiFactory.getInstance().switchProfile(profileId, parentToken),
+
+  // 观看时间表相关方法
+  getViewingSchedule: (profileId?: number) => ApiFactory.getInstance().getViewingSchedule(profileId),
+  setViewingSchedule: (schedule: ViewingSchedule, profileId?: number, parentToken?: string) => ApiFactory.getInstance().setViewingSchedule(schedule, profileId, parentToken),
+  grantScheduleExtension: (minutes: number, profileId?: number, parentToken?: string) => ApiFactory.getInstance().grantScheduleExtension(minutes, profileId, parentToken),
+
+  // 观看记录相关方法
+  recordWatchEvent: (event: WatchEvent) => ApiFactory.getInstance().recordWatchEvent(event),
+  getWatchHistory: (profileId?: number, limit?: number, offset?: number) => ApiFactory.getInstance().getWatchHistory(profileId, limit, offset),
+  getWatchSummary: (profileId?: number, days?: number) => ApiFactory.getInstance().getWatchSummary(profileId, days),
 
   // 测试方法
   testAPIConnections
@@ -6,7 +6,18 @@ import type {
   AppSettings,
   AIAnalysisRequest,
   AIAnalysisResponse,
-  Video 
+  Video,
+  ParentPinStatus,
+  ParentUnlock,
+  ScreenTimeStatus,
+  QuotaUsage,
+  ChildProfile,
+  ProfileInput,
+  ViewingSchedule,
+  ScheduleStatus,
+  WatchEvent,
+  WatchHistoryEntry,
+  WatchSummary
 } from '@/types'
 
 /**
@@ -18,19 +29,51 @@ export interface ApiService {
   searchVideos(request: SearchRequest): Promise<SearchResponse>
   analyzeVideo(request: AIAnalysisRequest): Promise<AIAnalysisResponse>
   saveVideo(video: Video): Promise<string>
-  deleteVideo(videoId: string): Promise<string>
+  deleteVideo(videoId: string, parentToken?: string): Promise<string>
   getCachedVideos(): Promise<Video[]> // 新增：获取所有缓存视频
 
   // 收藏相关
-  getFavorites(): Promise<FavoriteVideo[]>
-  addToFavorites(videoId: string, notes?: string): Promise<string>
-  removeFromFavorites(favoriteId: number): Promise<string>
+  getFavorites(profileId?: number, parentToken?: string): Promise<FavoriteVideo[]>
+  addToFavorites(videoId: string, notes?: string, profileId?: number, parentToken?: string): Promise<string>
+  removeFromFavorites(favoriteId: number, parentToken?: string): Promise<string>
 
   // 设置相关
   getSettings(): Promise<AppSettings>
-  saveSettings(settings: AppSettings): Promise<string>
+  saveSettings(settings: AppSettings, parentToken?: string): Promise<string>
 
   // 缓存相关
-  clearCache(): Promise<string>
-  getSearchHistory(limit?: number): Promise<Record<string, string>[]>
+  clearCache(parentToken?: string): Promise<string>
+  getSearchHistory(limit?: number, profileId?: number, parentToken?: string): Promise<Record<string, string>[]>
+
+  // 家长PIN相关
+  getParentPinStatus(parentToken?: string): Promise<ParentPinStatus>
+  setParentPin(pin: string, parentToken?: string): Promise<string>
+  unlockParent(pin: string): Promise<ParentUnlock>
+  lockParent(): Promise<string>
+
+  // 观看时长相关
+  startWatchSession(videoId?: string, profileId?: number, parentToken?: string): Promise<ScreenTimeStatus>
+  stopWatchSession(sessionId: number, parentToken?: string): Promise<ScreenTimeStatus>
+  getScreenTimeStatus(profileId?: number): Promise<ScreenTimeStatus>
+
+  // YouTube配额相关
+  getQuotaUsage(): Promise<QuotaUsage>
+
+  // 儿童档案相关
+  listProfiles(): Promise<ChildProfile[]>
+  getActiveProfile(): Promise<ChildProfile>
+  createProfile(profile: ProfileInput, parentToken?: string): Promise<ChildProfile>
+  updateProfile(profileId: number, profile: ProfileInput, parentToken?: string): Promise<ChildProfile>
+  deleteProfile(profileId: number, parentToken?: string): Promise<string>
+  switchProfile(profileId: number, parentToken?: string): Promise<ChildProfile>
+
+  // 观看时间表相关
+  getViewingSchedule(profileId?: number): Promise<ScheduleStatus>
+  setViewingSchedule(schedule: ViewingSchedule, profileId?: number, parentToken?: string): Promise<ScheduleStatus>
+  grantScheduleExtension(minutes: number, profileId?: number, parentToken?: string): Promise<ScheduleStatus>
+
+  // 观看记录相关
+  recordWatchEvent(event: WatchEvent): Promise<WatchHistoryEntry>
+  getWatchHistory(profileId?: number, limit?: number, offset?: number): Promise<WatchHistoryEntry[]>
+  getWatchSummary(profileId?: number, days?: number): Promise<WatchSummary>
 }
\ No newline at end of file
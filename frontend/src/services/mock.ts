@@ -6,7 +6,18 @@ import type {
   AppSettings,
   AIAnalysisRequest,
   AIAnalysisResponse,
-  Video 
+  Video,
+  ParentPinStatus,
+  ParentUnlock,
+  ScreenTimeStatus,
+  QuotaUsage,
+  ChildProfile,
+  ProfileInput,
+  ViewingSchedule,
+  ScheduleStatus,
+  WatchEvent,
+  WatchHistoryEntry,
+  WatchSummary
 } from '@/types'
 import type { ApiService } from './interfaces'
 
@@ -18,6 +29,14 @@ export class MockApiService implements ApiService {
   private favorites: FavoriteVideo[] = []
   private settings: AppSettings = this.getDefaultSettings()
   private searchHistory: Record<string, string>[] = []
+  private parentPin?: string
+  private parentToken?: string
+  private watchSessionId = 0
+  private profiles: ChildProfile[] = [this.createMockProfile(1, { name: '默认档案' })]
+  private activeProfileId = 1
+  private schedules: Record<number, ViewingSchedule> = {}
+  private graceUntil: Record<number, string> = {}
+  private watchHistory: WatchHistoryEntry[] = []
   
   constructor() {
     console.info('🌐 Browser API Service initialized - Frontend preview mode')
@@ -74,19 +93,21 @@ export class MockApiService implements ApiService {
   }
 
   // 获取收藏列表
-  async getFavorites(): Promise<FavoriteVideo[]> {
+  async getFavorites(profileId?: number): Promise<FavoriteVideo[]> {
     console.info('❤️ Mock: Loading favorites')
     await this.delay(300)
-    return [...this.favorites]
+    const ownerId = profileId ?? this.activeProfileId
+    return this.favorites.filter(fav => fav.profile_id === ownerId)
   }
 
   // 添加到收藏
-  async addToFavorites(videoId: string, notes?: string): Promise<string> {
+  async addToFavorites(videoId: string, notes?: string, profileId?: number): Promise<string> {
     console.info(`❤️ Mock: Adding video ${videoId} to favorites`)
     await this.delay(400)
     
     const newFavorite: FavoriteVideo = {
       id: Date.now(),
+      profile_id: profileId ?? this.activeProfileId,
       video_id: videoId,
       user_notes: notes,
       created_at: new Date().toISOString(),
@@ -138,8 +159,223 @@ export class MockApiService implements ApiService {
     return limit ? history.slice(0, limit) : history
   }
 
+  // 获取家长PIN状态
+  async getParentPinStatus(parentToken?: string): Promise<ParentPinStatus> {
+    await this.delay(100)
+    const pinSet = this.parentPin !== undefined
+    return {
+      pin_set: pinSet,
+      unlocked: !pinSet || (parentToken !== undefined && parentToken === this.parentToken),
+      lockout_seconds: 0
+    }
+  }
+
+  // 设置家长PIN
+  async setParentPin(pin: string, parentToken?: string): Promise<string> {
+    console.info('🔐 Mock: Setting parent PIN')
+    await this.delay(200)
+    if (this.parentPin !== undefined && parentToken !== this.parentToken) {
+      throw new Error('Parent PIN is required for this action')
+    }
+    this.parentPin = pin.trim()
+    this.parentToken = undefined
+    return 'Parent PIN saved successfully'
+  }
+
+  // 验证家长PIN
+  async unlockParent(pin: string): Promise<ParentUnlock> {
+    console.info('🔐 Mock: Unlocking parent mode')
+    await this.delay(200)
+    if (this.parentPin === undefined || pin.trim() !== this.parentPin) {
+      throw new Error('Incorrect parent PIN')
+    }
+    this.parentToken = `mock-${Date.now()}`
+    return { token: this.parentToken, expires_in_seconds: 600 }
+  }
+
+  // 结束家长解锁状态
+  async lockParent(): Promise<string> {
+    this.parentToken = undefined
+    return 'Parent session locked'
+  }
+
+  // 开始播放计时（预览模式不限制时长）
+  async startWatchSession(videoId?: string, profileId?: number): Promise<ScreenTimeStatus> {
+    console.info(`▶️ Mock: Starting watch session for ${videoId}`)
+    this.watchSessionId += 1
+    return { ...this.mockScreenTimeStatus(profileId), session_id: this.watchSessionId }
+  }
+
+  // 结束播放计时
+  async stopWatchSession(sessionId: number): Promise<ScreenTimeStatus> {
+    console.info(`⏹️ Mock: Stopping watch session ${sessionId}`)
+    return this.mockScreenTimeStatus()
+  }
+
+  // 获取今日观看时长
+  async getScreenTimeStatus(profileId?: number): Promise<ScreenTimeStatus> {
+    return this.mockScreenTimeStatus(profileId)
+  }
+
+  // 获取当日YouTube配额使用情况（预览模式不调用YouTube）
+  async getQuotaUsage(): Promise<QuotaUsage> {
+    return {
+      date: new Date().toISOString().slice(0, 10),
+      used_units: 0,
+      daily_budget: 10000,
+      remaining_units: 10000,
+      exceeded_policy: 'cache_only',
+      endpoints: []
+    }
+  }
+
+  // 获取所有儿童档案
+  async listProfiles(): Promise<ChildProfile[]> {
+    return this.profiles.map(profile => ({ ...profile, is_active: profile.id === this.activeProfileId }))
+  }
+
+  // 获取当前激活的档案
+  async getActiveProfile(): Promise<ChildProfile> {
+    return { ...this.findProfile(this.activeProfileId), is_active: true }
+  }
+
+  // 创建儿童档案
+  async createProfile(profile: ProfileInput): Promise<ChildProfile> {
+    console.info(`👶 Mock: Creating profile "${profile.name}"`)
+    const created = this.createMockProfile(Math.max(...this.profiles.map(p => p.id)) + 1, profile)
+    this.profiles.push(created)
+    return created
+  }
+
+  // 修改儿童档案
+  async updateProfile(profileId: number, profile: ProfileInput): Promise<ChildProfile> {
+    const existing = this.findProfile(profileId)
+    const updated = { ...existing, ...profile, updated_at: new Date().toISOString() }
+    this.profiles = this.profiles.map(p => (p.id === profileId ? updated : p))
+    return updated
+  }
+
+  // 删除儿童档案，删除当前档案时切换到剩下的第一个档案
+  async deleteProfile(profileId: number): Promise<string> {
+    const profile = this.findProfile(profileId)
+    if (this.profiles.length <= 1) {
+      throw new Error('Cannot delete the last profile')
+    }
+    this.profiles = this.profiles.filter(p => p.id !== profileId)
+    this.favorites = this.favorites.filter(fav => fav.profile_id !== profileId)
+    if (profileId === this.activeProfileId) {
+      this.activeProfileId = Math.min(...this.profiles.map(p => p.id))
+    }
+    return `Deleted profile '${profile.name}'`
+  }
+
+  // 切换当前档案
+  async switchProfile(profileId: number): Promise<ChildProfile> {
+    this.activeProfileId = this.findProfile(profileId).id
+    return this.getActiveProfile()
+  }
+
+  // 获取观看时间表（预览模式只保存，不判断当前时间）
+  async getViewingSchedule(profileId?: number): Promise<ScheduleStatus> {
+    const ownerId = profileId ?? this.activeProfileId
+    return {
+      profile_id: ownerId,
+      schedule: this.schedules[ownerId] ?? { windows: [] },
+      in_window: true,
+      grace_until: this.graceUntil[ownerId],
+      allowed: true
+    }
+  }
+
+  // 保存观看时间表
+  async setViewingSchedule(schedule: ViewingSchedule, profileId?: number): Promise<ScheduleStatus> {
+    console.info('🗓️ Mock: Saving viewing schedule')
+    const ownerId = profileId ?? this.activeProfileId
+    this.schedules[ownerId] = schedule
+    return this.getViewingSchedule(ownerId)
+  }
+
+  // 临时延长观看时间
+  async grantScheduleExtension(minutes: number, profileId?: number): Promise<ScheduleStatus> {
+    const ownerId = profileId ?? this.activeProfileId
+    this.graceUntil[ownerId] = new Date(Date.now() + minutes * 60 * 1000).toISOString()
+    return this.getViewingSchedule(ownerId)
+  }
+
+  // 记录观看进度，进度只增不减
+  async recordWatchEvent(event: WatchEvent): Promise<WatchHistoryEntry> {
+    const profileId = event.profile_id ?? this.activeProfileId
+    const startedAt = event.started_at ?? new Date().toISOString()
+    const existing = this.watchHistory.find(entry =>
+      entry.profile_id === profileId && entry.video_id === event.video_id && entry.started_at === startedAt
+    )
+    if (existing) {
+      existing.watched_seconds = Math.max(existing.watched_seconds, event.watched_seconds)
+      existing.completed = existing.completed || event.completed
+      return { ...existing }
+    }
+    const entry: WatchHistoryEntry = {
+      id: this.watchHistory.length + 1,
+      profile_id: profileId,
+      video_id: event.video_id,
+      started_at: startedAt,
+      watched_seconds: event.watched_seconds,
+      completed: event.completed,
+      video: event.video ?? this.findVideoById(event.video_id)
+    }
+    this.watchHistory.push(entry)
+    return { ...entry }
+  }
+
+  // 获取观看记录
+  async getWatchHistory(profileId?: number, limit = 50, offset = 0): Promise<WatchHistoryEntry[]> {
+    const ownerId = profileId ?? this.activeProfileId
+    return this.watchHistory
+      .filter(entry => entry.profile_id === ownerId)
+      .sort((a, b) => b.started_at.localeCompare(a.started_at))
+      .slice(offset, offset + limit)
+  }
+
+  // 获取观看统计（预览模式只汇总总数）
+  async getWatchSummary(profileId?: number, days = 7): Promise<WatchSummary> {
+    const ownerId = profileId ?? this.activeProfileId
+    const since = new Date(Date.now() - days * 24 * 60 * 60 * 1000).toISOString()
+    const entries = this.watchHistory.filter(entry => entry.profile_id === ownerId && entry.started_at >= since)
+    return {
+      profile_id: ownerId,
+      since,
+      total_seconds: entries.reduce((total, entry) => total + entry.watched_seconds, 0),
+      videos_watched: new Set(entries.map(entry => entry.video_id)).size,
+      completed_count: entries.filter(entry => entry.completed).length,
+      daily: [],
+      top_channels: []
+    }
+  }
+
   // === 私有辅助方法 ===
 
+  private createMockProfile(id: number, input: ProfileInput): ChildProfile {
+    const now = new Date().toISOString()
+    return { ...input, id, is_active: false, created_at: now, updated_at: now }
+  }
+
+  private findProfile(profileId: number): ChildProfile {
+    const profile = this.profiles.find(p => p.id === profileId)
+    if (!profile) {
+      throw new Error(`Profile ${profileId} does not exist`)
+    }
+    return profile
+  }
+
+  private mockScreenTimeStatus(profileId?: number): ScreenTimeStatus {
+    return {
+      profile_id: profileId ?? 1,
+      date: new Date().toISOString().slice(0, 10),
+      used_seconds: 0,
+      locked: false
+    }
+  }
+
   private async delay(ms: number): Promise<void> {
     return new Promise(resolve => setTimeout(resolve, ms))
   }
@@ -6,7 +6,20 @@ import type {
   AppSettings,
   AIAnalysisRequest,
   AIAnalysisResponse,
-  Video 
+  Video,
+  AppError,
+  AppErrorCode,
+  ParentPinStatus,
+  ParentUnlock,
+  ScreenTimeStatus,
+  QuotaUsage,
+  ChildProfile,
+  ProfileInput,
+  ViewingSchedule,
+  ScheduleStatus,
+  WatchEvent,
+  WatchHistoryEntry,
+  WatchSummary
 } from '@/types'
 import type { ApiService } from './interfaces'
 
@@ -37,6 +50,42 @@ const getTauriInvoke = async () => {
   }
 }
 
+/**
+ * 命令调用失败时抛出的错误
+ * 保留后端返回的错误代码，调用方可以据此给出可操作的提示（如去设置页配置密钥）
+ */
+export class AppApiError extends Error {
+  readonly code?: AppErrorCode
+  readonly detail: string
+
+  constructor(context: string, error: unknown) {
+    const appError = AppApiError.asAppError(error)
+    const detail = appError ? appError.message : AppApiError.describe(error)
+    super(`${context}: ${detail}`)
+    this.name = 'AppApiError'
+    this.code = appError?.code
+    this.detail = detail
+  }
+
+  // 后端命令返回的结构化错误 { code, message }
+  private static asAppError(error: unknown): AppError | undefined {
+    if (error && typeof error === 'object' && 'code' in error && 'message' in error) {
+      return error as AppError
+    }
+    return undefined
+  }
+
+  private static describe(error: unknown): string {
+    if (typeof error === 'string') {
+      return error
+    }
+    if (error instanceof Error) {
+      return error.message
+    }
+    return String(error)
+  }
+}
+
 /**
  * Tauri API服务实现
  * 通过Tauri调用Rust后端代码
@@ -66,7 +115,7 @@ export class TauriApiService implements ApiService {
       return response
     } catch (error) {
       console.error('Production search failed:', error)
-      throw new Error(`搜索失败: ${this.formatError(error)}`)
+      throw new AppApiError('搜索失败', error)
     }
   }
 
@@ -78,7 +127,7 @@ export class TauriApiService implements ApiService {
       return await invoke<AIAnalysisResponse>('analyze_video', { request })
     } catch (error) {
       console.error('Tauri analyze video failed:', error)
-      throw new Error(`视频分析失败: ${this.formatError(error)}`)
+      throw new AppApiError('视频分析失败', error)
     }
   }
 
@@ -90,19 +139,19 @@ export class TauriApiService implements ApiService {
       return await invoke<string>('save_video', { video })
     } catch (error) {
       console.error('Tauri save video failed:', error)
-      throw new Error(`保存视频失败: ${this.formatError(error)}`)
+      throw new AppApiError('保存视频失败', error)
     }
   }
 
   // 删除视频
-  async deleteVideo(videoId: string): Promise<string> {
+  async deleteVideo(videoId: string, parentToken?: string): Promise<string> {
     try {
       console.info(`🗑️ Tauri: Deleting video ${videoId}`)
       const invoke = await getTauriInvoke()
-      return await invoke<string>('delete_video', { videoId })
+      return await invoke<string>('delete_video', { videoId, parentToken })
     } catch (error) {
       console.error('Tauri delete video failed:', error)
-      throw new Error(`删除视频失败: ${this.formatError(error)}`)
+      throw new AppApiError('删除视频失败', error)
     }
   }
 
@@ -121,15 +170,15 @@ export class TauriApiService implements ApiService {
     }
   }
 
-  // 获取收藏列表
-  async getFavorites(): Promise<FavoriteVideo[]> {
+  // 获取收藏列表，profileId 为空时使用当前档案，查看其他档案需要家长解锁
+  async getFavorites(profileId?: number, parentToken?: string): Promise<FavoriteVideo[]> {
     try {
       console.info('❤️ Production: Loading favorites...')
       
       const invoke = await getTauriInvoke()
       console.info('✅ Tauri invoke obtained, calling get_favorites command...')
       
-      const favorites = await invoke<FavoriteVideo[]>('get_favorites')
+      const favorites = await invoke<FavoriteVideo[]>('get_favorites', { profileId, parentToken })
       console.info(`✅ Loaded ${favorites.length} favorites from backend`)
       
       return favorites
@@ -143,26 +192,26 @@ export class TauriApiService implements ApiService {
   }
 
   // 添加到收藏
-  async addToFavorites(videoId: string, notes?: string): Promise<string> {
+  async addToFavorites(videoId: string, notes?: string, profileId?: number, parentToken?: string): Promise<string> {
     try {
       console.info(`❤️ Tauri: Adding video ${videoId} to favorites`)
       const invoke = await getTauriInvoke()
-      return await invoke<string>('add_to_favorites', { videoId, notes })
+      return await invoke<string>('add_to_favorites', { videoId, notes, profileId, parentToken })
     } catch (error) {
       console.error('Tauri add to favorites failed:', error)
-      throw new Error(`添加收藏失败: ${this.formatError(error)}`)
+      throw new AppApiError('添加收藏失败', error)
     }
   }
 
   // 从收藏中移除
-  async removeFromFavorites(favoriteId: number): Promise<string> {
+  async removeFromFavorites(favoriteId: number, parentToken?: string): Promise<string> {
     try {
       console.info(`🗑️ Tauri: Removing favorite ${favoriteId}`)
       const invoke = await getTauriInvoke()
-      return await invoke<string>('remove_from_favorites', { favoriteId })
+      return await invoke<string>('remove_from_favorites', { favoriteId, parentToken })
     } catch (error) {
       console.error('Tauri remove from favorites failed:', error)
-      throw new Error(`移除收藏失败: ${this.formatError(error)}`)
+      throw new AppApiError('移除收藏失败', error)
     }
   }
 
@@ -229,49 +278,269 @@ export class TauriApiService implements ApiService {
   }
 
   // 保存设置
-  async saveSettings(settings: AppSettings): Promise<string> {
+  async saveSettings(settings: AppSettings, parentToken?: string): Promise<string> {
     try {
       console.info('⚙️ Tauri: Saving settings')
       const invoke = await getTauriInvoke()
-      return await invoke<string>('save_settings', { settings })
+      return await invoke<string>('save_settings', { settings, parentToken })
     } catch (error) {
       console.error('Tauri save settings failed:', error)
-      throw new Error(`保存设置失败: ${this.formatError(error)}`)
+      throw new AppApiError('保存设置失败', error)
     }
   }
 
   // 清除缓存
-  async clearCache(): Promise<string> {
+  async clearCache(parentToken?: string): Promise<string> {
     try {
       console.info('🧹 Tauri: Clearing cache')
       const invoke = await getTauriInvoke()
-      return await invoke<string>('clear_cache')
+      return await invoke<string>('clear_cache', { parentToken })
     } catch (error) {
       console.error('Tauri clear cache failed:', error)
-      throw new Error(`清除缓存失败: ${this.formatError(error)}`)
+      throw new AppApiError('清除缓存失败', error)
     }
   }
 
-  // 获取搜索历史
-  async getSearchHistory(limit?: number): Promise<Record<string, string>[]> {
+  // 获取搜索历史，profileId 为空时使用当前档案，查看其他档案需要家长解锁
+  async getSearchHistory(limit?: number, profileId?: number, parentToken?: string): Promise<Record<string, string>[]> {
     try {
       console.info('📚 Tauri: Loading search history')
       const invoke = await getTauriInvoke()
-      return await invoke<Record<string, string>[]>('get_search_history', { limit })
+      return await invoke<Record<string, string>[]>('get_search_history', { limit, profileId, parentToken })
     } catch (error) {
       console.error('Tauri get search history failed:', error)
-      throw new Error(`获取搜索历史失败: ${this.formatError(error)}`)
+      throw new AppApiError('获取搜索历史失败', error)
     }
   }
 
-  // 私有方法：格式化错误信息
-  private formatError(error: unknown): string {
-    if (typeof error === 'string') {
-      return error
+  // 获取家长PIN状态（是否已设置、令牌是否有效、剩余锁定时间）
+  async getParentPinStatus(parentToken?: string): Promise<ParentPinStatus> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<ParentPinStatus>('get_parent_pin_status', { parentToken })
+    } catch (error) {
+      console.error('Tauri get parent PIN status failed:', error)
+      throw new AppApiError('获取家长PIN状态失败', error)
     }
-    if (error instanceof Error) {
-      return error.message
+  }
+
+  // 设置或修改家长PIN，已有PIN时需要解锁令牌
+  async setParentPin(pin: string, parentToken?: string): Promise<string> {
+    try {
+      console.info('🔐 Tauri: Setting parent PIN')
+      const invoke = await getTauriInvoke()
+      return await invoke<string>('set_parent_pin', { pin, parentToken })
+    } catch (error) {
+      console.error('Tauri set parent PIN failed:', error)
+      throw new AppApiError('设置家长PIN失败', error)
+    }
+  }
+
+  // 验证家长PIN，成功后返回短期解锁令牌
+  async unlockParent(pin: string): Promise<ParentUnlock> {
+    try {
+      console.info('🔐 Tauri: Unlocking parent mode')
+      const invoke = await getTauriInvoke()
+      return await invoke<ParentUnlock>('unlock_parent', { pin })
+    } catch (error) {
+      console.error('Tauri unlock parent failed:', error)
+      throw new AppApiError('家长解锁失败', error)
+    }
+  }
+
+  // 结束家长解锁状态
+  async lockParent(): Promise<string> {
+    try {
+      console.info('🔒 Tauri: Locking parent mode')
+      const invoke = await getTauriInvoke()
+      return await invoke<string>('lock_parent')
+    } catch (error) {
+      console.error('Tauri lock parent failed:', error)
+      throw new AppApiError('家长锁定失败', error)
+    }
+  }
+
+  // 开始播放计时，超出每日时长或不在允许时段时后端拒绝
+  async startWatchSession(videoId?: string, profileId?: number, parentToken?: string): Promise<ScreenTimeStatus> {
+    try {
+      console.info(`▶️ Tauri: Starting watch session for ${videoId}`)
+      const invoke = await getTauriInvoke()
+      return await invoke<ScreenTimeStatus>('start_watch_session', { videoId, profileId, parentToken })
+    } catch (error) {
+      console.error('Tauri start watch session failed:', error)
+      throw new AppApiError('开始观看失败', error)
+    }
+  }
+
+  // 结束播放计时
+  async stopWatchSession(sessionId: number, parentToken?: string): Promise<ScreenTimeStatus> {
+    try {
+      console.info(`⏹️ Tauri: Stopping watch session ${sessionId}`)
+      const invoke = await getTauriInvoke()
+      return await invoke<ScreenTimeStatus>('stop_watch_session', { sessionId, parentToken })
+    } catch (error) {
+      console.error('Tauri stop watch session failed:', error)
+      throw new AppApiError('结束观看失败', error)
+    }
+  }
+
+  // 获取今日观看时长
+  async getScreenTimeStatus(profileId?: number): Promise<ScreenTimeStatus> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<ScreenTimeStatus>('get_screen_time_status', { profileId })
+    } catch (error) {
+      console.error('Tauri get screen time status failed:', error)
+      throw new AppApiError('获取观看时长失败', error)
+    }
+  }
+
+  // 获取当日YouTube配额使用情况
+  async getQuotaUsage(): Promise<QuotaUsage> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<QuotaUsage>('get_quota_usage')
+    } catch (error) {
+      console.error('Tauri get quota usage failed:', error)
+      throw new AppApiError('获取配额使用情况失败', error)
+    }
+  }
+
+  // 获取所有儿童档案
+  async listProfiles(): Promise<ChildProfile[]> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<ChildProfile[]>('list_profiles')
+    } catch (error) {
+      console.error('Tauri list profiles failed:', error)
+      throw new AppApiError('获取儿童档案失败', error)
+    }
+  }
+
+  // 获取当前激活的档案
+  async getActiveProfile(): Promise<ChildProfile> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<ChildProfile>('get_active_profile')
+    } catch (error) {
+      console.error('Tauri get active profile failed:', error)
+      throw new AppApiError('获取当前档案失败', error)
+    }
+  }
+
+  // 创建儿童档案（需要家长解锁）
+  async createProfile(profile: ProfileInput, parentToken?: string): Promise<ChildProfile> {
+    try {
+      console.info(`👶 Tauri: Creating profile "${profile.name}"`)
+      const invoke = await getTauriInvoke()
+      return await invoke<ChildProfile>('create_profile', { profile, parentToken })
+    } catch (error) {
+      console.error('Tauri create profile failed:', error)
+      throw new AppApiError('创建档案失败', error)
+    }
+  }
+
+  // 修改儿童档案（需要家长解锁）
+  async updateProfile(profileId: number, profile: ProfileInput, parentToken?: string): Promise<ChildProfile> {
+    try {
+      console.info(`👶 Tauri: Updating profile ${profileId}`)
+      const invoke = await getTauriInvoke()
+      return await invoke<ChildProfile>('update_profile', { profileId, profile, parentToken })
+    } catch (error) {
+      console.error('Tauri update profile failed:', error)
+      throw new AppApiError('修改档案失败', error)
+    }
+  }
+
+  // 删除儿童档案及其收藏和历史（需要家长解锁）
+  async deleteProfile(profileId: number, parentToken?: string): Promise<string> {
+    try {
+      console.info(`🗑️ Tauri: Deleting profile ${profileId}`)
+      const invoke = await getTauriInvoke()
+      return await invoke<string>('delete_profile', { profileId, parentToken })
+    } catch (error) {
+      console.error('Tauri delete profile failed:', error)
+      throw new AppApiError('删除档案失败', error)
+    }
+  }
+
+  // 切换当前档案（需要家长解锁）
+  async switchProfile(profileId: number, parentToken?: string): Promise<ChildProfile> {
+    try {
+      console.info(`🔄 Tauri: Switching to profile ${profileId}`)
+      const invoke = await getTauriInvoke()
+      return await invoke<ChildProfile>('switch_profile', { profileId, parentToken })
+    } catch (error) {
+      console.error('Tauri switch profile failed:', error)
+      throw new AppApiError('切换档案失败', error)
+    }
+  }
+
+  // 获取档案的观看时间表及当前是否允许观看
+  async getViewingSchedule(profileId?: number): Promise<ScheduleStatus> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<ScheduleStatus>('get_viewing_schedule', { profileId })
+    } catch (error) {
+      console.error('Tauri get viewing schedule failed:', error)
+      throw new AppApiError('获取观看时间表失败', error)
+    }
+  }
+
+  // 保存观看时间表（需要家长解锁）
+  async setViewingSchedule(schedule: ViewingSchedule, profileId?: number, parentToken?: string): Promise<ScheduleStatus> {
+    try {
+      console.info('🗓️ Tauri: Saving viewing schedule')
+      const invoke = await getTauriInvoke()
+      return await invoke<ScheduleStatus>('set_viewing_schedule', { profileId, schedule, parentToken })
+    } catch (error) {
+      console.error('Tauri set viewing schedule failed:', error)
+      throw new AppApiError('保存观看时间表失败', error)
+    }
+  }
+
+  // 家长临时延长观看时间
+  async grantScheduleExtension(minutes: number, profileId?: number, parentToken?: string): Promise<ScheduleStatus> {
+    try {
+      console.info(`⏳ Tauri: Granting ${minutes} extra minutes`)
+      const invoke = await getTauriInvoke()
+      return await invoke<ScheduleStatus>('grant_schedule_extension', { profileId, minutes, parentToken })
+    } catch (error) {
+      console.error('Tauri grant schedule extension failed:', error)
+      throw new AppApiError('延长观看时间失败', error)
+    }
+  }
+
+  // 上报观看进度，同一次观看以 started_at 区分
+  async recordWatchEvent(event: WatchEvent): Promise<WatchHistoryEntry> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<WatchHistoryEntry>('record_watch_event', { event })
+    } catch (error) {
+      console.error('Tauri record watch event failed:', error)
+      throw new AppApiError('记录观看失败', error)
+    }
+  }
+
+  // 获取观看记录，按开始时间倒序
+  async getWatchHistory(profileId?: number, limit?: number, offset?: number): Promise<WatchHistoryEntry[]> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<WatchHistoryEntry[]>('get_watch_history', { profileId, limit, offset })
+    } catch (error) {
+      console.error('Tauri get watch history failed:', error)
+      throw new AppApiError('获取观看记录失败', error)
+    }
+  }
+
+  // 获取最近几天的观看统计
+  async getWatchSummary(profileId?: number, days?: number): Promise<WatchSummary> {
+    try {
+      const invoke = await getTauriInvoke()
+      return await invoke<WatchSummary>('get_watch_summary', { profileId, days })
+    } catch (error) {
+      console.error('Tauri get watch summary failed:', error)
+      throw new AppApiError('获取观看统计失败', error)
     }
-    return String(error)
   }
 }
\ No newline at end of file
@@ -19,6 +19,7 @@ interface AppStore {
   settings: AppSettings;
   loading: LoadingState;
   searchHistory: SearchHistory[];
+  parentToken?: string; // 家长解锁令牌，只保存在内存中
 
   // Actions
   setCurrentSearch: (query: string) => void;
@@ -30,6 +31,8 @@ interface AppStore {
   removeFromFavorites: (favoriteId: number) => Promise<void>;
   loadSettings: () => Promise<void>;
   saveSettings: (settings: AppSettings) => Promise<void>;
+  unlockParent: (pin: string) => Promise<string>;
+  ensureParentUnlocked: () => Promise<boolean>;
   clearCache: () => Promise<void>; // 清空数据库缓存和搜索结果
   loadSearchHistory: () => Promise<void>;
   setLoading: (key: keyof LoadingState, value: boolean) => void;
@@ -125,7 +128,7 @@ export const useAppStore = create<AppStore>()(
 
         deleteVideo: async (videoId: string) => {
           try {
-            await api.deleteVideo(videoId);
+            await api.deleteVideo(videoId, get().parentToken);
 
             // 从搜索结果中移除
             set(state => ({
@@ -177,7 +180,7 @@ export const useAppStore = create<AppStore>()(
 
         removeFromFavorites: async (favoriteId: number) => {
           try {
-            await api.removeFromFavorites(favoriteId);
+            await api.removeFromFavorites(favoriteId, get().parentToken);
             await get().loadFavorites();
           } catch (error) {
             console.error('Failed to remove from favorites:', error);
@@ -202,7 +205,7 @@ export const useAppStore = create<AppStore>()(
           }));
 
           try {
-            await api.saveSettings(settings);
+            await api.saveSettings(settings, get().parentToken);
             set({ settings });
           } catch (error) {
             console.error('Failed to save settings:', error);
@@ -214,10 +217,26 @@ export const useAppStore = create<AppStore>()(
           }
         },
 
+        // 验证家长PIN，令牌随之后的特权命令一起发送
+        unlockParent: async (pin: string) => {
+          const unlock = await api.unlockParent(pin);
+          set({ parentToken: unlock.token });
+          return unlock.token;
+        },
+
+        // 未设置PIN或令牌仍然有效时返回 true，否则需要先输入PIN
+        ensureParentUnlocked: async () => {
+          const status = await api.getParentPinStatus(get().parentToken);
+          if (!status.unlocked) {
+            set({ parentToken: undefined });
+          }
+          return status.unlocked;
+        },
+
         clearCache: async () => {
           try {
             console.log('🗑️ Clearing database cache and search results...');
-            await api.clearCache();
+            await api.clearCache(get().parentToken);
             // 同时清空前端显示的搜索结果
             set({ searchResults: [] });
             console.log('✅ Cache and results cleared');
@@ -232,6 +251,7 @@ export const useAppStore = create<AppStore>()(
             const history = await api.getSearchHistory(20);
             set({ searchHistory: history.map(item => ({
               id: parseInt(item.id || '0'),
+              profile_id: parseInt(item.profile_id || '0'),
               query: item.query || '',
               platform: item.platform || 'youtube',
               filter_mode: item.filter_mode || 'balanced',
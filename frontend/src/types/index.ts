@@ -16,6 +16,17 @@ export interface Video {
   tags?: string;
   cached_at?: string;
   subtitles?: string;
+  analysis_status?: 'verified' | 'unverified'; // unverified 表示AI未评分或结果无法解析
+  ai_reasoning?: string; // AI给出的通过/拒绝理由
+  recommended_age?: string;
+  age_fit_score?: number; // 0-1
+  quality_score?: number; // 0-1
+  platform?: 'youtube' | 'youtube_kids';
+  made_for_kids?: boolean;
+  age_restricted?: boolean;
+  embeddable?: boolean;
+  topic_categories?: string[];
+  warning?: string; // 未验证视频策略为 "warn" 时后端附带的提示
 }
 
 // 搜索相关类型
@@ -25,6 +36,9 @@ export interface SearchRequest {
   filter_mode: 'strict' | 'balanced' | 'educational';
   max_results?: number;
   skip_ai_analysis?: boolean; // 临时禁用AI分析，直接返回搜索结果
+  page_token?: string; // 从上次返回的 next_page_token 继续搜索
+  profile_id?: number; // 为空时使用当前激活的档案，指定其他档案需要家长解锁
+  parent_token?: string;
 }
 
 export interface SearchResponse {
@@ -32,6 +46,27 @@ export interface SearchResponse {
   total_found: number;
   search_time: number;
   ai_analysis_time: number;
+  next_page_token?: string; // 还有更多结果时用于加载下一页
+}
+
+// 后端命令失败时返回的错误代码
+export type AppErrorCode =
+  | 'MissingApiKey'
+  | 'QuotaExceeded'
+  | 'InvalidKey'
+  | 'Network'
+  | 'ProviderParse'
+  | 'Database'
+  | 'InvalidInput'
+  | 'ParentLocked'
+  | 'ScreenTimeExceeded'
+  | 'OutsideSchedule'
+  | 'Internal';
+
+// 后端命令失败时返回的结构化错误
+export interface AppError {
+  code: AppErrorCode;
+  message: string;
 }
 
 // AI分析相关类型
@@ -59,6 +94,7 @@ export interface AIAnalysisResponse {
 // 收藏相关类型
 export interface FavoriteVideo {
   id: number;
+  profile_id: number;
   video_id: string;
   user_notes?: string;
   created_at: string;
@@ -82,6 +118,7 @@ export interface AppSettings {
   // 搜索配置
   default_platforms: string[]; // ["youtube", "youtube_kids"]
   search_language: 'zh' | 'en' | 'both';
+  search_region?: string; // YouTube地区代码，如 'US'、'TW'，留空表示不限地区
   min_duration: number; // 最短时长（分钟）
   max_duration: number; // 最长时长（分钟）
   
@@ -94,6 +131,8 @@ export interface AppSettings {
   enable_visual_alarm: boolean;
   enable_vibration_alarm: boolean;
   alarm_message: string; // 自定义闹钟消息
+  daily_screen_time_minutes?: number; // 每日观看时长上限（分钟），0 表示不限制
+  screen_time_warning_minutes?: number; // 剩余时长低于该值时提醒
   
   // 高级设置
   enable_notifications: boolean;
@@ -124,6 +163,7 @@ export interface FilterPreset {
 // 搜索历史类型
 export interface SearchHistory {
   id: number;
+  profile_id: number;
   query: string;
   platform: string;
   filter_mode: string;
@@ -131,6 +171,108 @@ export interface SearchHistory {
   created_at: string;
 }
 
+// 儿童档案类型 - 为空的字段沿用全局设置
+export interface ChildProfile {
+  id: number;
+  name: string;
+  child_age?: string;
+  custom_filter_prompt?: string;
+  min_duration?: number;
+  max_duration?: number;
+  filter_mode?: 'strict' | 'balanced' | 'educational';
+  unverified_video_policy?: 'block' | 'warn' | 'allow';
+  daily_screen_time_minutes?: number;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export type ProfileInput = Omit<ChildProfile, 'id' | 'is_active' | 'created_at' | 'updated_at'>;
+
+// 家长PIN解锁结果，token 需要随特权命令一起传给后端
+export interface ParentUnlock {
+  token: string;
+  expires_in_seconds: number;
+}
+
+export interface ParentPinStatus {
+  pin_set: boolean;
+  unlocked: boolean;
+  lockout_seconds: number;
+}
+
+// 当日YouTube配额使用情况，date 为太平洋时间的日期
+export interface QuotaUsage {
+  date: string;
+  used_units: number;
+  daily_budget: number;
+  remaining_units: number;
+  exceeded_policy: 'cache_only' | 'block';
+  endpoints: { endpoint: string; calls: number; units: number }[];
+}
+
+// 观看时长状态，同时也是 screen-time-warning / screen-time-countdown / screen-time-lockout 事件的内容
+export interface ScreenTimeStatus {
+  profile_id: number;
+  date: string;
+  used_seconds: number;
+  limit_seconds?: number; // 为空表示不限制
+  remaining_seconds?: number;
+  locked: boolean;
+  session_id?: number;
+}
+
+// 允许观看的时间段：weekdays 为 1（周一）到 7（周日），时间为 HH:MM
+export interface ViewingWindow {
+  weekdays: number[];
+  start: string;
+  end: string;
+}
+
+export interface ViewingSchedule {
+  timezone?: string; // IANA 时区名，为空时使用系统时区
+  windows: ViewingWindow[]; // 为空表示不限制
+}
+
+// 时间表状态，也是 viewing-schedule-lockout 事件的内容
+export interface ScheduleStatus {
+  profile_id: number;
+  schedule: ViewingSchedule;
+  in_window: boolean;
+  grace_until?: string;
+  allowed: boolean;
+}
+
+// 观看记录上报，同一次观看多次上报时 started_at 保持不变
+export interface WatchEvent {
+  video_id: string;
+  profile_id?: number;
+  started_at?: string;
+  watched_seconds: number;
+  completed: boolean;
+  video?: Video;
+}
+
+export interface WatchHistoryEntry {
+  id: number;
+  profile_id: number;
+  video_id: string;
+  started_at: string;
+  watched_seconds: number;
+  completed: boolean;
+  video?: Video;
+}
+
+export interface WatchSummary {
+  profile_id: number;
+  since: string;
+  total_seconds: number;
+  videos_watched: number;
+  completed_count: number;
+  daily: { date: string; watched_seconds: number; videos: number }[];
+  top_channels: { channel_title: string; watched_seconds: number; videos: number }[];
+}
+
 // UI状态类型
 export interface LoadingState {
   searching: boolean;
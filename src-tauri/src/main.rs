@@ -1,17 +1,18 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri::{command, Manager, AppHandle};
+use tauri::{command, Emitter, Manager, AppHandle};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use reqwest::Client;
 use anyhow::Result;
-use std::path::PathBuf;
-use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use std::path::{Path, PathBuf};
+use sqlx::{Row, SqliteConnection, SqlitePool, sqlite::{SqlitePoolOptions, SqliteRow}};
 use std::sync::Arc;
+use futures::StreamExt;
 
 // 数据模型定义
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Video {
     pub id: String,
     pub title: String,
@@ -29,6 +30,41 @@ pub struct Video {
     pub tags: Option<String>,
     pub cached_at: Option<String>,
     pub subtitles: Option<String>,
+    pub analysis_status: Option<String>, // "verified" 或 "unverified"（AI未评分或结果无法解析）
+    pub ai_reasoning: Option<String>, // AI给出的通过/拒绝理由
+    pub recommended_age: Option<String>,
+    pub age_fit_score: Option<f64>, // 年龄适宜度 0-1
+    pub quality_score: Option<f64>, // 制作质量 0-1
+    pub platform: Option<String>, // "youtube" 或 "youtube_kids"
+    pub made_for_kids: Option<bool>, // YouTube status.madeForKids
+    pub age_restricted: Option<bool>, // contentDetails.contentRating.ytRating 为 ytAgeRestricted
+    pub embeddable: Option<bool>, // status.embeddable
+    pub topic_categories: Option<Vec<String>>, // topicDetails.topicCategories（维基百科词条名）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>, // 按 "warn" 策略放行的未验证视频附带的提示，不保存到数据库
+}
+
+// AI分析状态
+const ANALYSIS_VERIFIED: &str = "verified";
+const ANALYSIS_UNVERIFIED: &str = "unverified";
+
+impl Video {
+    fn is_verified(&self) -> bool {
+        self.analysis_status.as_deref() == Some(ANALYSIS_VERIFIED)
+    }
+
+    // 清除评分并标记为未验证，避免把缺失的结果当作通过
+    fn mark_unverified(&mut self) {
+        self.ai_score = None;
+        self.education_score = None;
+        self.safety_score = None;
+        self.age_appropriate = None;
+        self.ai_reasoning = None;
+        self.recommended_age = None;
+        self.age_fit_score = None;
+        self.quality_score = None;
+        self.analysis_status = Some(ANALYSIS_UNVERIFIED.to_string());
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,8 +72,11 @@ pub struct SearchRequest {
     pub query: String,
     pub platform: String,
     pub filter_mode: String,
-    pub max_results: Option<i32>,
+    pub max_results: Option<i32>, // 需要的视频数量，默认为设置中的 video_count
     pub skip_ai_analysis: Option<bool>, // 临时禁用AI分析，直接返回搜索结果
+    pub page_token: Option<String>, // 从上次返回的 next_page_token 继续搜索
+    pub profile_id: Option<i64>, // 为空时使用当前激活的档案，指定其他档案需要家长解锁
+    pub parent_token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -46,6 +85,7 @@ pub struct SearchResponse {
     pub total_found: i32,
     pub search_time: f64,
     pub ai_analysis_time: f64,
+    pub next_page_token: Option<String>, // 还有更多结果时用于加载下一页
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -62,6 +102,7 @@ pub struct AIAnalysisRequest {
     pub view_count: Option<i64>,
     pub like_count: Option<i64>,
     pub published_at: Option<String>,
+    pub profile_id: Option<i64>, // 为空时使用当前激活的档案
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -82,6 +123,7 @@ pub struct BatchAnalysisRequest {
     pub filter_prompt: Option<String>,
     pub min_duration: Option<i32>,
     pub max_duration: Option<i32>,
+    pub profile_id: Option<i64>, // 为空时使用当前激活的档案
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -89,21 +131,256 @@ pub struct BatchAnalysisResponse {
     pub analyzed_videos: Vec<Video>,
     pub total_analyzed: i32,
     pub analysis_time: f64,
+    pub invalid_items: Vec<InvalidBatchItem>,
+    pub failed_chunks: Vec<FailedChunk>,
+}
+
+// 整块请求失败的视频（这些视频被标记为未验证，chunk_index 从0开始）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailedChunk {
+    pub chunk_index: usize,
+    pub video_ids: Vec<String>,
+    pub error: String,
+}
+
+// 批量分析结果中无法使用的条目（index 为提示词中的视频序号，从1开始）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvalidBatchItem {
+    pub index: Option<usize>,
+    pub video_id: Option<String>,
+    pub error: String,
+}
+
+// 命令返回给前端的错误，序列化为 {"code": "QuotaExceeded", "message": "..."}
+// 前端根据 code 显示可操作的提示（如去设置页配置密钥）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "code", content = "message")]
+pub enum AppError {
+    MissingApiKey(String),
+    QuotaExceeded(String),
+    InvalidKey(String),
+    Network(String),
+    ProviderParse(String),
+    Database(String),
+    InvalidInput(String),
+    ParentLocked(String),
+    ScreenTimeExceeded(String),
+    OutsideSchedule(String),
+    Internal(String),
+}
+
+impl AppError {
+    fn code(&self) -> &'static str {
+        match self {
+            AppError::MissingApiKey(_) => "MissingApiKey",
+            AppError::QuotaExceeded(_) => "QuotaExceeded",
+            AppError::InvalidKey(_) => "InvalidKey",
+            AppError::Network(_) => "Network",
+            AppError::ProviderParse(_) => "ProviderParse",
+            AppError::Database(_) => "Database",
+            AppError::InvalidInput(_) => "InvalidInput",
+            AppError::ParentLocked(_) => "ParentLocked",
+            AppError::ScreenTimeExceeded(_) => "ScreenTimeExceeded",
+            AppError::OutsideSchedule(_) => "OutsideSchedule",
+            AppError::Internal(_) => "Internal",
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            AppError::MissingApiKey(message)
+            | AppError::QuotaExceeded(message)
+            | AppError::InvalidKey(message)
+            | AppError::Network(message)
+            | AppError::ProviderParse(message)
+            | AppError::Database(message)
+            | AppError::InvalidInput(message)
+            | AppError::ParentLocked(message)
+            | AppError::ScreenTimeExceeded(message)
+            | AppError::OutsideSchedule(message)
+            | AppError::Internal(message) => message,
+        }
+    }
+
+    // 内部函数返回 anyhow 错误：已分类的 AppError 原样取出，其余按调用处给定的类别归类
+    fn classify(error: anyhow::Error, fallback: impl FnOnce(String) -> AppError) -> AppError {
+        match error.chain().find_map(|cause| cause.downcast_ref::<AppError>()) {
+            Some(app_error) => app_error.clone(),
+            None => fallback(error.to_string()),
+        }
+    }
+
+    fn is_quota_or_key_error(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<AppError>(),
+            Some(AppError::QuotaExceeded(_) | AppError::InvalidKey(_))
+        )
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> Self {
+        AppError::Database(error.to_string())
+    }
+}
+
+// AI接口返回非成功状态时的错误分类
+fn provider_status_error(provider_name: &str, status: reqwest::StatusCode, body: &str) -> AppError {
+    let message = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|data| data["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| status.to_string());
+
+    match status {
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+            AppError::InvalidKey(format!("{} rejected the API key: {}", provider_name, message))
+        }
+        reqwest::StatusCode::TOO_MANY_REQUESTS => {
+            AppError::QuotaExceeded(format!("{} rate limit or quota exceeded: {}", provider_name, message))
+        }
+        _ => AppError::Network(format!("{} API error ({}): {}", provider_name, status, message)),
+    }
+}
+
+// 检查YouTube API响应状态，区分配额耗尽、密钥无效和其他错误
+async fn check_youtube_response(response: reqwest::Response, endpoint: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let data: serde_json::Value = response.json().await.unwrap_or_default();
+    let reason = data["error"]["errors"][0]["reason"].as_str().unwrap_or_default();
+    let message = data["error"]["message"].as_str().unwrap_or("Unknown error");
+
+    let error = match reason {
+        "quotaExceeded" | "dailyLimitExceeded" | "rateLimitExceeded" | "userRateLimitExceeded" => {
+            AppError::QuotaExceeded(format!("YouTube {} quota exceeded: {}", endpoint, message))
+        }
+        "keyInvalid" | "keyExpired" | "accessNotConfigured" | "ipRefererBlocked" => {
+            AppError::InvalidKey(format!("YouTube API key rejected: {}", message))
+        }
+        // 无效密钥通常返回 400 badRequest，消息为 "API key not valid"
+        _ if message.contains("API key") || status == reqwest::StatusCode::FORBIDDEN => {
+            AppError::InvalidKey(format!("YouTube API key rejected: {}", message))
+        }
+        _ => AppError::Network(format!("YouTube {} error ({}): {}", endpoint, status, message)),
+    };
+    Err(error.into())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BatchVideoAnalysis {
+    #[serde(deserialize_with = "deserialize_lenient_number")]
     pub index: usize,
+    #[serde(deserialize_with = "deserialize_lenient_number")]
     pub score: f64,
     pub suitable: bool,
+    #[serde(default)]
     pub reason: String,
+    #[serde(deserialize_with = "deserialize_lenient_number")]
     pub educational_value: f64,
+    #[serde(deserialize_with = "deserialize_lenient_number")]
     pub safety_score: f64,
+    #[serde(default)]
+    pub age_fit: Option<f64>,
+    #[serde(default)]
+    pub production_quality: Option<f64>,
+    #[serde(default)]
+    pub recommended_age: Option<String>,
+}
+
+impl BatchVideoAnalysis {
+    // 校验序号和各项评分范围（0-100）
+    fn validate(&self, video_count: usize) -> std::result::Result<(), String> {
+        if self.index == 0 || self.index > video_count {
+            return Err(format!("index {} is out of range 1..={}", self.index, video_count));
+        }
+        for (field, value) in [
+            ("score", Some(self.score)),
+            ("educational_value", Some(self.educational_value)),
+            ("safety_score", Some(self.safety_score)),
+            ("age_fit", self.age_fit),
+            ("production_quality", self.production_quality),
+        ] {
+            let Some(value) = value else { continue };
+            if !(0.0..=100.0).contains(&value) {
+                return Err(format!("{} {} is out of range 0..=100", field, value));
+            }
+        }
+        Ok(())
+    }
+}
+
+// 与 BatchVideoAnalysis 对应的批量评分输出结构
+fn batch_analysis_output() -> StructuredOutput {
+    StructuredOutput {
+        name: "record_video_analysis",
+        description: "Record the suitability analysis for each video in the list",
+        schema: serde_json::json!({
+            "type": "object",
+            "additionalProperties": false,
+            "required": ["videos"],
+            "properties": {
+                "videos": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "required": [
+                            "index", "score", "suitable", "reason", "educational_value", "safety_score",
+                            "age_fit", "production_quality", "recommended_age"
+                        ],
+                        "properties": {
+                            "index": { "type": "integer", "description": "视频序号，从1开始" },
+                            "score": { "type": "number", "description": "总分 0-100" },
+                            "suitable": { "type": "boolean" },
+                            "reason": { "type": "string" },
+                            "educational_value": { "type": "number", "description": "教育价值 0-100" },
+                            "safety_score": { "type": "number", "description": "内容安全 0-100" },
+                            "age_fit": { "type": "number", "description": "年龄适宜 0-100" },
+                            "production_quality": { "type": "number", "description": "制作质量 0-100" },
+                            "recommended_age": { "type": "string", "description": "推荐年龄段，如 3-6岁" }
+                        }
+                    }
+                }
+            }
+        }),
+    }
+}
+
+// 模型有时把数字写成字符串（如 "85"），这里同时接受两种写法
+fn deserialize_lenient_number<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr + serde::Deserialize<'de>,
+    T::Err: std::fmt::Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString<T> {
+        Number(T),
+        String(String),
+    }
+
+    match NumberOrString::<T>::deserialize(deserializer)? {
+        NumberOrString::Number(value) => Ok(value),
+        NumberOrString::String(text) => text.trim().parse().map_err(serde::de::Error::custom),
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FavoriteVideo {
     pub id: i32,
+    pub profile_id: i64,
     pub video_id: String,
     pub user_notes: Option<String>,
     pub created_at: String,
@@ -111,22 +388,40 @@ pub struct FavoriteVideo {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)] // 旧版配置文件缺少的字段使用默认值
 pub struct AppSettings {
     // API配置
     pub openai_api_key: Option<String>,
     pub anthropic_api_key: Option<String>,
     pub youtube_api_key: Option<String>,
     pub ai_provider: String, // "openai" 或 "anthropic"
+    pub openai_base_url: String, // OpenAI兼容接口地址，可指向 Ollama / LM Studio / vLLM
+    
+    // 模型配置（按提供商）
+    pub openai_model: String,
+    pub openai_temperature: f32, // 0.0 - 2.0
+    pub openai_max_tokens: u32, // 单次请求输出token上限
+    pub anthropic_model: String,
+    pub anthropic_temperature: f32, // 0.0 - 1.0
+    pub anthropic_max_tokens: u32,
+    pub use_structured_output: bool, // 批量评分使用JSON Schema（OpenAI）或工具调用（Anthropic）保证输出格式
+    pub ai_max_concurrency: u32, // 同时发送的分析请求数上限
     
     // 过滤条件配置
     pub child_age: String, // "2-4", "3-6", "4-8", "6-10", "8-12"
     pub custom_filter_prompt: Option<String>,
     pub video_count: i32, // 每次筛选视频数量
+    pub search_quota_budget: i32, // 单次搜索（含自动翻页）最多消耗的YouTube配额单位
+    pub youtube_daily_quota: i32, // 每日YouTube配额预算（默认项目配额为10000单位）
+    pub quota_exceeded_policy: String, // 超出每日预算时："cache_only"（只返回缓存结果）, "block"（拒绝搜索）
     pub cache_duration_hours: i32, // 缓存有效期（小时）
+    pub ai_cache_days: i32, // AI分析结果缓存天数，0 表示不使用缓存
+    pub unverified_video_policy: String, // 未经AI验证的视频："block"（屏蔽）, "warn"（显示并提示）, "allow"（放行）
     
     // 搜索配置
     pub default_platforms: Vec<String>, // ["youtube", "youtube_kids"]
     pub search_language: String, // "zh", "en", "both"
+    pub search_region: String, // YouTube regionCode（ISO 3166-1 二位代码，如 "US"、"TW"），留空表示不限地区
     pub min_duration: i32, // 最短时长（分钟）
     pub max_duration: i32, // 最长时长（分钟）
     
@@ -139,6 +434,8 @@ pub struct AppSettings {
     pub enable_visual_alarm: bool,
     pub enable_vibration_alarm: bool,
     pub alarm_message: String, // 自定义闹钟消息
+    pub daily_screen_time_minutes: i32, // 每日观看时长上限（分钟），0 表示不限制，由后端强制执行
+    pub screen_time_warning_minutes: i32, // 剩余时长低于该值时发出提醒
     
     // 高级设置
     pub enable_notifications: bool,
@@ -162,16 +459,33 @@ impl Default for AppSettings {
             anthropic_api_key: None,
             youtube_api_key: None,
             ai_provider: "openai".to_string(),
+            openai_base_url: OPENAI_DEFAULT_BASE_URL.to_string(),
+            
+            // 模型配置
+            openai_model: "gpt-3.5-turbo".to_string(),
+            openai_temperature: 0.3,
+            openai_max_tokens: 8000,
+            anthropic_model: "claude-3-haiku-20240307".to_string(),
+            anthropic_temperature: 0.3,
+            anthropic_max_tokens: 4096,
+            use_structured_output: true,
+            ai_max_concurrency: 3,
             
             // 过滤条件配置
             child_age: "3-6".to_string(),
             custom_filter_prompt: Some(default_filter_prompt()),
             video_count: 10,
+            search_quota_budget: 3 * SEARCH_PAGE_QUOTA_COST,
+            youtube_daily_quota: 10000,
+            quota_exceeded_policy: "cache_only".to_string(),
             cache_duration_hours: 24,
+            ai_cache_days: 30,
+            unverified_video_policy: "block".to_string(),
             
             // 搜索配置
             default_platforms: vec!["youtube".to_string(), "youtube_kids".to_string()],
             search_language: "zh".to_string(),
+            search_region: "US".to_string(),
             min_duration: 2,
             max_duration: 30,
             
@@ -184,6 +498,8 @@ impl Default for AppSettings {
             enable_visual_alarm: true,
             enable_vibration_alarm: false,
             alarm_message: "该休息了，小朋友！".to_string(),
+            daily_screen_time_minutes: 0,
+            screen_time_warning_minutes: 5,
             
             // 高级设置
             enable_notifications: true,
@@ -201,16 +517,200 @@ impl Default for AppSettings {
     }
 }
 
+// OpenAI官方接口地址
+const OPENAI_DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+// 单次请求允许配置的最大输出token数
+const MAX_CONFIGURABLE_TOKENS: u32 = 32000;
+// 设置中允许的最大并发分析请求数
+const MAX_AI_CONCURRENCY: u32 = 8;
+
+impl AppSettings {
+    // 保存前校验配置
+    fn validate(&self) -> Result<()> {
+        if self.openai_model.trim().is_empty() {
+            return Err(anyhow::anyhow!("OpenAI model must not be empty"));
+        }
+        if self.anthropic_model.trim().is_empty() {
+            return Err(anyhow::anyhow!("Anthropic model must not be empty"));
+        }
+        if !(0.0..=2.0).contains(&self.openai_temperature) {
+            return Err(anyhow::anyhow!(
+                "OpenAI temperature must be between 0 and 2, got {}",
+                self.openai_temperature
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.anthropic_temperature) {
+            return Err(anyhow::anyhow!(
+                "Anthropic temperature must be between 0 and 1, got {}",
+                self.anthropic_temperature
+            ));
+        }
+        for (name, value) in [
+            ("OpenAI", self.openai_max_tokens),
+            ("Anthropic", self.anthropic_max_tokens),
+        ] {
+            if value == 0 || value > MAX_CONFIGURABLE_TOKENS {
+                return Err(anyhow::anyhow!(
+                    "{} max tokens must be between 1 and {}, got {}",
+                    name,
+                    MAX_CONFIGURABLE_TOKENS,
+                    value
+                ));
+            }
+        }
+        if self.ai_max_concurrency == 0 || self.ai_max_concurrency > MAX_AI_CONCURRENCY {
+            return Err(anyhow::anyhow!(
+                "AI concurrency must be between 1 and {}, got {}",
+                MAX_AI_CONCURRENCY,
+                self.ai_max_concurrency
+            ));
+        }
+        if self.youtube_daily_quota <= 0 {
+            return Err(anyhow::anyhow!("YouTube daily quota must be positive"));
+        }
+        if !["cache_only", "block"].contains(&self.quota_exceeded_policy.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Unknown quota exceeded policy '{}'",
+                self.quota_exceeded_policy
+            ));
+        }
+        if self.min_duration < 0 || self.min_duration > self.max_duration {
+            return Err(anyhow::anyhow!(
+                "Invalid duration range {}-{} minutes",
+                self.min_duration,
+                self.max_duration
+            ));
+        }
+        if !["zh", "en", "both"].contains(&self.search_language.as_str()) {
+            return Err(anyhow::anyhow!("Unknown search language '{}'", self.search_language));
+        }
+        let region = self.search_region.trim();
+        let valid_region = region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic());
+        if !region.is_empty() && !valid_region {
+            return Err(anyhow::anyhow!(
+                "Search region must be a two-letter country code, got '{}'",
+                self.search_region
+            ));
+        }
+        if self.daily_screen_time_minutes < 0 || self.screen_time_warning_minutes < 0 {
+            return Err(anyhow::anyhow!("Screen time limits must not be negative"));
+        }
+        if self.ai_cache_days < 0 {
+            return Err(anyhow::anyhow!("AI cache days must not be negative"));
+        }
+        if !["block", "warn", "allow"].contains(&self.unverified_video_policy.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Unknown unverified video policy '{}'",
+                self.unverified_video_policy
+            ));
+        }
+        if !self.openai_base_url.trim().is_empty() {
+            url::Url::parse(self.openai_base_url.trim())
+                .map_err(|e| anyhow::anyhow!("Invalid OpenAI base URL '{}': {}", self.openai_base_url, e))?;
+        }
+        Ok(())
+    }
+}
+
 // 默认过滤提示词
+// 其中的 {占位符} 在分析时由 PromptContext 根据设置渲染
 fn default_filter_prompt() -> String {
-    r#"请分析以下视频是否适合3-6岁儿童观看。评判标准：
+    r#"请分析以下视频是否适合{age_range}岁{age_group}观看。评判标准：
+1. 教育价值：是否有助于学习认知、语言、数学、科学等
+2. 内容安全：无暴力、恐怖、不当内容
+3. 年龄适宜：{age_criteria}
+4. 时长合适：建议{min_duration}-{max_duration}分钟
+5. 制作质量：画面清晰、音频清楚、制作精良
+6. 语言：优先{language}内容
+7. 筛选模式：{filter_mode}
+
+请对每个视频给出0-100的评分，并说明理由。"#.to_string()
+}
+
+// 旧版本写入配置文件的默认提示词（固定为3-6岁），加载时升级为模板
+const LEGACY_DEFAULT_FILTER_PROMPT: &str = r#"请分析以下视频是否适合3-6岁儿童观看。评判标准：
 1. 教育价值：是否有助于学习认知、语言、数学、科学等
 2. 内容安全：无暴力、恐怖、不当内容
 3. 年龄适宜：符合学前儿童认知水平
 4. 时长合适：建议2-20分钟
 5. 制作质量：画面清晰、音频清楚、制作精良
 
-请对每个视频给出0-100的评分，并说明理由。只返回评分高于70分的视频。"#.to_string()
+请对每个视频给出0-100的评分，并说明理由。只返回评分高于70分的视频。"#;
+
+// 提示词模板渲染上下文
+// 支持的占位符：{age_range} {age_group} {age_criteria} {language} {min_duration} {max_duration} {filter_mode}
+#[derive(Debug, Clone)]
+struct PromptContext {
+    age_range: String,
+    language: String,
+    min_duration: i32,
+    max_duration: i32,
+    filter_mode: String,
+}
+
+impl PromptContext {
+    fn from_settings(settings: &AppSettings, filter_mode: &str) -> Self {
+        Self {
+            age_range: settings.child_age.clone(),
+            language: settings.search_language.clone(),
+            min_duration: settings.min_duration,
+            max_duration: settings.max_duration,
+            filter_mode: filter_mode.to_string(),
+        }
+    }
+
+    // 各年龄段的称呼
+    fn age_group(&self) -> &'static str {
+        match self.age_range.as_str() {
+            "2-4" => "幼儿",
+            "3-6" => "学前儿童",
+            "4-8" => "学龄前及低年级儿童",
+            "6-10" => "小学生",
+            "8-12" => "小学中高年级儿童",
+            _ => "儿童",
+        }
+    }
+
+    // 各年龄段的“年龄适宜”评判标准
+    fn age_criteria(&self) -> &'static str {
+        match self.age_range.as_str() {
+            "2-4" => "画面简单、节奏舒缓、语言简短重复，适合幼儿的注意力和理解能力",
+            "3-6" => "符合学前儿童认知水平，内容直观易懂",
+            "4-8" => "适合刚开始识字和学习数数的儿童，可包含简单的故事和常识",
+            "6-10" => "符合小学生的知识水平，可包含科学、自然、阅读等入门内容",
+            "8-12" => "可包含较深入的科学、历史、逻辑和创作内容，但避免成人主题和过度刺激",
+            _ => "符合目标年龄儿童的认知水平",
+        }
+    }
+
+    fn language_label(&self) -> &'static str {
+        match self.language.as_str() {
+            "zh" => "中文",
+            "en" => "英文",
+            _ => "中文或英文",
+        }
+    }
+
+    fn filter_mode_label(&self) -> &'static str {
+        match self.filter_mode.as_str() {
+            "strict" => "严格（安全性优先）",
+            "educational" => "教育优先（教育价值优先）",
+            _ => "平衡（兼顾安全性与教育价值）",
+        }
+    }
+
+    // 只替换已知占位符，提示词中的JSON示例等其他花括号保持不变
+    fn render(&self, template: &str) -> String {
+        template
+            .replace("{age_range}", &self.age_range)
+            .replace("{age_group}", self.age_group())
+            .replace("{age_criteria}", self.age_criteria())
+            .replace("{language}", self.language_label())
+            .replace("{min_duration}", &self.min_duration.to_string())
+            .replace("{max_duration}", &self.max_duration.to_string())
+            .replace("{filter_mode}", self.filter_mode_label())
+    }
 }
 
 // 数据库相关结构
@@ -219,7 +719,7 @@ pub struct CachedVideo {
     pub id: String,
     pub query: String,
     pub platform: String,
-    pub video_data: String, // JSON序列化的Video数据
+    pub filter_mode: Option<String>,
     pub cached_at: String,
     pub expires_at: String,
 }
@@ -227,6 +727,7 @@ pub struct CachedVideo {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchHistoryEntry {
     pub id: i64,
+    pub profile_id: i64,
     pub query: String,
     pub platform: String,
     pub filter_mode: String,
@@ -234,12 +735,135 @@ pub struct SearchHistoryEntry {
     pub created_at: String,
 }
 
+// 儿童档案，可为空的字段沿用全局设置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChildProfile {
+    pub id: i64,
+    pub name: String,
+    pub child_age: Option<String>, // "2-4", "3-6", "4-8", "6-10", "8-12"
+    pub custom_filter_prompt: Option<String>,
+    pub min_duration: Option<i32>, // 最短时长（分钟）
+    pub max_duration: Option<i32>, // 最长时长（分钟）
+    pub filter_mode: Option<String>, // "strict", "balanced", "educational"
+    pub unverified_video_policy: Option<String>, // "block", "warn", "allow"
+    pub daily_screen_time_minutes: Option<i32>, // 每日观看时长上限（分钟），0 表示不限制
+    pub is_active: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// 创建或修改档案时提交的字段
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProfileInput {
+    pub name: String,
+    pub child_age: Option<String>,
+    pub custom_filter_prompt: Option<String>,
+    pub min_duration: Option<i32>,
+    pub max_duration: Option<i32>,
+    pub filter_mode: Option<String>,
+    pub unverified_video_policy: Option<String>,
+    pub daily_screen_time_minutes: Option<i32>,
+}
+
+const CHILD_AGE_RANGES: &[&str] = &["2-4", "3-6", "4-8", "6-10", "8-12"];
+
+impl ChildProfile {
+    // 以全局设置为基础叠加档案中的覆盖项，后续搜索和分析只使用合并后的设置
+    fn apply_to(&self, settings: &AppSettings) -> AppSettings {
+        let mut effective = settings.clone();
+        if let Some(child_age) = &self.child_age {
+            effective.child_age = child_age.clone();
+        }
+        if self.custom_filter_prompt.is_some() {
+            effective.custom_filter_prompt = self.custom_filter_prompt.clone();
+        }
+        if let Some(min_duration) = self.min_duration {
+            effective.min_duration = min_duration;
+        }
+        if let Some(max_duration) = self.max_duration {
+            effective.max_duration = max_duration;
+        }
+        if let Some(filter_mode) = &self.filter_mode {
+            effective.default_filter_mode = filter_mode.clone();
+        }
+        if let Some(policy) = &self.unverified_video_policy {
+            effective.unverified_video_policy = policy.clone();
+        }
+        if let Some(minutes) = self.daily_screen_time_minutes {
+            effective.daily_screen_time_minutes = minutes;
+        }
+        effective
+    }
+}
+
+impl ProfileInput {
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("Profile name must not be empty"));
+        }
+        if let Some(child_age) = &self.child_age {
+            if !CHILD_AGE_RANGES.contains(&child_age.as_str()) {
+                return Err(anyhow::anyhow!("Unknown age range '{}'", child_age));
+            }
+        }
+        if let Some(filter_mode) = &self.filter_mode {
+            if !["strict", "balanced", "educational"].contains(&filter_mode.as_str()) {
+                return Err(anyhow::anyhow!("Unknown filter mode '{}'", filter_mode));
+            }
+        }
+        if let Some(policy) = &self.unverified_video_policy {
+            if !["block", "warn", "allow"].contains(&policy.as_str()) {
+                return Err(anyhow::anyhow!("Unknown unverified video policy '{}'", policy));
+            }
+        }
+        if self.min_duration.is_some_and(|min| min < 0) {
+            return Err(anyhow::anyhow!("Minimum duration must not be negative"));
+        }
+        if self.daily_screen_time_minutes.is_some_and(|minutes| minutes < 0) {
+            return Err(anyhow::anyhow!("Screen time limit must not be negative"));
+        }
+        if let (Some(min), Some(max)) = (self.min_duration, self.max_duration) {
+            if min > max {
+                return Err(anyhow::anyhow!("Invalid duration range {}-{} minutes", min, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+// 当日YouTube配额使用情况（date 为太平洋时间的日期）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub date: String,
+    pub used_units: i64,
+    pub daily_budget: i64,
+    pub remaining_units: i64,
+    pub exceeded_policy: String,
+    pub endpoints: Vec<EndpointQuotaUsage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EndpointQuotaUsage {
+    pub endpoint: String,
+    pub calls: i64,
+    pub units: i64,
+}
+
 // YouTube API响应结构
 #[derive(Debug, Deserialize)]
 struct YouTubeSearchResponse {
     items: Vec<YouTubeVideoItem>,
     #[serde(rename = "pageInfo")]
     page_info: PageInfo,
+    #[serde(rename = "nextPageToken")]
+    next_page_token: Option<String>,
+}
+
+// 一页搜索结果
+#[derive(Debug, Default)]
+struct YouTubeSearchPage {
+    videos: Vec<Video>,
+    next_page_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -294,6 +918,9 @@ struct YouTubeVideoDetail {
     #[serde(rename = "contentDetails")]
     content_details: Option<YouTubeContentDetails>,
     snippet: Option<YouTubeVideoSnippet>,
+    status: Option<YouTubeVideoStatus>,
+    #[serde(rename = "topicDetails")]
+    topic_details: Option<YouTubeTopicDetails>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -342,6 +969,89 @@ struct YouTubeStatistics {
 #[derive(Debug, Deserialize)]
 struct YouTubeContentDetails {
     duration: String,
+    #[serde(rename = "contentRating")]
+    content_rating: Option<YouTubeContentRating>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YouTubeContentRating {
+    #[serde(rename = "ytRating")]
+    yt_rating: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YouTubeVideoStatus {
+    #[serde(rename = "madeForKids")]
+    made_for_kids: Option<bool>,
+    embeddable: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YouTubeTopicDetails {
+    #[serde(rename = "topicCategories", default)]
+    topic_categories: Vec<String>,
+}
+
+// videos.list 请求的 part：status 和 topicDetails 提供不依赖AI的安全信号
+const VIDEO_DETAIL_PARTS: &str = "snippet,contentDetails,statistics,status,topicDetails";
+
+impl YouTubeVideoDetail {
+    fn made_for_kids(&self) -> Option<bool> {
+        self.status.as_ref().and_then(|s| s.made_for_kids)
+    }
+
+    fn embeddable(&self) -> Option<bool> {
+        self.status.as_ref().and_then(|s| s.embeddable)
+    }
+
+    // 有 contentDetails 时才能判断是否年龄限制
+    fn age_restricted(&self) -> Option<bool> {
+        self.content_details.as_ref().map(|cd| {
+            cd.content_rating
+                .as_ref()
+                .and_then(|rating| rating.yt_rating.as_deref())
+                == Some("ytAgeRestricted")
+        })
+    }
+
+    // https://en.wikipedia.org/wiki/Children's_music -> Children's music
+    fn topic_categories(&self) -> Option<Vec<String>> {
+        let topics = self.topic_details.as_ref()?;
+        Some(
+            topics
+                .topic_categories
+                .iter()
+                .map(|url| {
+                    let name = url.rsplit('/').next().unwrap_or(url);
+                    urlencoding::decode(name)
+                        .map(|decoded| decoded.into_owned())
+                        .unwrap_or_else(|_| name.to_string())
+                        .replace('_', " ")
+                })
+                .collect(),
+        )
+    }
+
+    fn apply_signals_to(&self, video: &mut Video) {
+        video.made_for_kids = self.made_for_kids();
+        video.age_restricted = self.age_restricted();
+        video.embeddable = self.embeddable();
+        video.topic_categories = self.topic_categories();
+    }
+}
+
+// 确定性的安全预筛选：年龄限制或禁止嵌入播放的视频不发送给AI，直接丢弃
+fn prefilter_videos(videos: Vec<Video>) -> Vec<Video> {
+    let total = videos.len();
+    let kept: Vec<Video> = videos
+        .into_iter()
+        .filter(|video| video.age_restricted != Some(true) && video.embeddable != Some(false))
+        .collect();
+
+    if kept.len() < total {
+        println!("🛡️ Pre-filter dropped {} age-restricted or non-embeddable videos", total - kept.len());
+    }
+    kept
 }
 
 // OpenAI API响应结构
@@ -351,6 +1061,8 @@ struct OpenAIRequest {
     messages: Vec<OpenAIMessage>,
     max_tokens: u32,
     temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -374,7 +1086,19 @@ struct OpenAIChoice {
 struct AnthropicRequest {
     model: String,
     max_tokens: u32,
+    temperature: f32,
     messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -388,9 +1112,13 @@ struct AnthropicResponse {
     content: Vec<AnthropicContent>,
 }
 
+// 内容块：普通文本为 "text"，工具调用为 "tool_use"
 #[derive(Debug, Deserialize)]
 struct AnthropicContent {
-    text: String,
+    #[serde(rename = "type")]
+    content_type: String,
+    text: Option<String>,
+    input: Option<serde_json::Value>,
 }
 
 // 应用状态管理
@@ -399,6 +1127,10 @@ struct AppState {
     client: Client,
     app_data_dir: PathBuf,
     db: Arc<SqlitePool>,
+    providers: ProviderRegistry,
+    parent_lock: std::sync::Mutex<ParentLock>,
+    screen_time: std::sync::Mutex<ScreenTimeTracker>,
+    clock: Arc<dyn Clock>,
 }
 
 impl AppState {
@@ -425,7 +1157,7 @@ impl AppState {
             .map_err(|e| anyhow::anyhow!("Failed to connect to database: {}", e))?;
         
         // 运行数据库迁移
-        run_migrations(&db)
+        run_migrations(&db, &db_path)
             .await
             .map_err(|e| anyhow::anyhow!("Failed to run database migrations: {}", e))?;
         
@@ -434,104 +1166,844 @@ impl AppState {
             client: Client::new(),
             app_data_dir,
             db: Arc::new(db),
+            providers: ProviderRegistry::new(),
+            parent_lock: std::sync::Mutex::new(ParentLock::default()),
+            screen_time: std::sync::Mutex::new(ScreenTimeTracker::default()),
+            clock: Arc::new(SystemClock),
         })
     }
     
     fn config_file_path(&self) -> PathBuf {
         self.app_data_dir.join("config.json")
     }
+    
+    fn parent_pin_path(&self) -> PathBuf {
+        self.app_data_dir.join("parent_pin.json")
+    }
+}
+
+// 数据库迁移步骤
+// v1-v4 早于版本记录，旧安装没有 schema_version 会重放它们，因此这些步骤必须幂等；
+// 之后的版本在事务中只执行一次
+enum MigrationStep {
+    Sql(&'static str),
+    // SQLite 不支持 ADD COLUMN IF NOT EXISTS，需要先检查列是否存在
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+    // 列已不存在时跳过
+    DropColumn {
+        table: &'static str,
+        column: &'static str,
+    },
+}
+
+struct Migration {
+    version: i64,
+    description: &'static str,
+    steps: &'static [MigrationStep],
 }
 
-// 数据库迁移 - 使用SQLx执行
-async fn run_migrations(pool: &SqlitePool) -> Result<()> {
+// 按版本号顺序排列，只能在末尾追加新版本，不要修改已发布的迁移
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        steps: &[
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS cached_videos (
+                    id TEXT PRIMARY KEY,
+                    query TEXT NOT NULL,
+                    platform TEXT NOT NULL,
+                    video_data TEXT NOT NULL,
+                    cached_at TEXT NOT NULL,
+                    expires_at TEXT NOT NULL
+                )
+                "#,
+            ),
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS favorites (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    video_id TEXT NOT NULL UNIQUE,
+                    user_notes TEXT,
+                    created_at TEXT NOT NULL,
+                    video_data TEXT NOT NULL
+                )
+                "#,
+            ),
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS search_history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    query TEXT NOT NULL,
+                    platform TEXT NOT NULL,
+                    filter_mode TEXT NOT NULL,
+                    results_count INTEGER NOT NULL,
+                    created_at TEXT NOT NULL
+                )
+                "#,
+            ),
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS usage_stats (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    event_type TEXT NOT NULL,
+                    event_data TEXT,
+                    created_at TEXT NOT NULL
+                )
+                "#,
+            ),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_cached_videos_query ON cached_videos(query, platform)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_cached_videos_expires ON cached_videos(expires_at)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_search_history_date ON search_history(created_at)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_usage_stats_date ON usage_stats(created_at)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_favorites_created ON favorites(created_at)"),
+        ],
+    },
+    Migration {
+        version: 2,
+        description: "AI analysis cache",
+        steps: &[
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS ai_analyses (
+                    video_id TEXT NOT NULL,
+                    provider TEXT NOT NULL,
+                    model TEXT NOT NULL,
+                    prompt_hash TEXT NOT NULL,
+                    ai_score REAL NOT NULL,
+                    education_score REAL NOT NULL,
+                    safety_score REAL NOT NULL,
+                    approved INTEGER NOT NULL,
+                    reasoning TEXT,
+                    recommended_age TEXT,
+                    age_fit_score REAL,
+                    quality_score REAL,
+                    analyzed_at TEXT NOT NULL,
+                    PRIMARY KEY (video_id, provider, model, prompt_hash)
+                )
+                "#,
+            ),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_ai_analyses_date ON ai_analyses(analyzed_at)"),
+        ],
+    },
+    Migration {
+        version: 3,
+        description: "filter mode on cached videos",
+        steps: &[MigrationStep::AddColumn {
+            table: "cached_videos",
+            column: "filter_mode",
+            definition: "TEXT",
+        }],
+    },
+    Migration {
+        version: 4,
+        description: "YouTube quota usage",
+        steps: &[
+            // quota_date 为太平洋时间的日期
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS youtube_quota_usage (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    quota_date TEXT NOT NULL,
+                    endpoint TEXT NOT NULL,
+                    units INTEGER NOT NULL,
+                    created_at TEXT NOT NULL
+                )
+                "#,
+            ),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_youtube_quota_date ON youtube_quota_usage(quota_date)"),
+        ],
+    },
+    Migration {
+        version: 5,
+        description: "normalized videos table",
+        steps: &[
+            // 视频元数据与评分只保存一份，缓存、收藏和搜索历史通过视频ID引用
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS videos (
+                    id TEXT PRIMARY KEY,
+                    title TEXT NOT NULL,
+                    description TEXT,
+                    thumbnail_url TEXT,
+                    duration INTEGER,
+                    channel_title TEXT,
+                    published_at TEXT,
+                    view_count INTEGER,
+                    like_count INTEGER,
+                    ai_score REAL,
+                    education_score REAL,
+                    safety_score REAL,
+                    age_appropriate INTEGER,
+                    tags TEXT,
+                    subtitles TEXT,
+                    analysis_status TEXT,
+                    ai_reasoning TEXT,
+                    recommended_age TEXT,
+                    age_fit_score REAL,
+                    quality_score REAL,
+                    platform TEXT,
+                    made_for_kids INTEGER,
+                    age_restricted INTEGER,
+                    embeddable INTEGER,
+                    topic_categories TEXT,
+                    updated_at TEXT NOT NULL
+                )
+                "#,
+            ),
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS search_history_videos (
+                    history_id INTEGER NOT NULL REFERENCES search_history(id) ON DELETE CASCADE,
+                    video_id TEXT NOT NULL REFERENCES videos(id),
+                    position INTEGER NOT NULL,
+                    PRIMARY KEY (history_id, video_id)
+                )
+                "#,
+            ),
+            // 拆解旧的 video_data JSON，缓存中的副本比收藏中的更新，优先保留
+            MigrationStep::Sql(
+                r#"
+                INSERT OR IGNORE INTO videos (
+                    id, title, description, thumbnail_url, duration, channel_title, published_at,
+                    view_count, like_count, ai_score, education_score, safety_score, age_appropriate,
+                    tags, subtitles, analysis_status, ai_reasoning, recommended_age, age_fit_score,
+                    quality_score, platform, made_for_kids, age_restricted, embeddable, topic_categories,
+                    updated_at
+                )
+                SELECT
+                    legacy.video_id,
+                    COALESCE(json_extract(legacy.data, '$.title'), 'Unknown Video'),
+                    json_extract(legacy.data, '$.description'),
+                    json_extract(legacy.data, '$.thumbnail_url'),
+                    json_extract(legacy.data, '$.duration'),
+                    json_extract(legacy.data, '$.channel_title'),
+                    json_extract(legacy.data, '$.published_at'),
+                    json_extract(legacy.data, '$.view_count'),
+                    json_extract(legacy.data, '$.like_count'),
+                    json_extract(legacy.data, '$.ai_score'),
+                    json_extract(legacy.data, '$.education_score'),
+                    json_extract(legacy.data, '$.safety_score'),
+                    json_extract(legacy.data, '$.age_appropriate'),
+                    json_extract(legacy.data, '$.tags'),
+                    json_extract(legacy.data, '$.subtitles'),
+                    json_extract(legacy.data, '$.analysis_status'),
+                    json_extract(legacy.data, '$.ai_reasoning'),
+                    json_extract(legacy.data, '$.recommended_age'),
+                    json_extract(legacy.data, '$.age_fit_score'),
+                    json_extract(legacy.data, '$.quality_score'),
+                    COALESCE(json_extract(legacy.data, '$.platform'), legacy.platform),
+                    json_extract(legacy.data, '$.made_for_kids'),
+                    json_extract(legacy.data, '$.age_restricted'),
+                    json_extract(legacy.data, '$.embeddable'),
+                    json_extract(legacy.data, '$.topic_categories'),
+                    legacy.updated_at
+                FROM (
+                    SELECT id AS video_id, video_data AS data, platform, cached_at AS updated_at, 0 AS priority
+                    FROM cached_videos
+                    UNION ALL
+                    SELECT video_id, video_data, NULL, created_at, 1
+                    FROM favorites
+                ) AS legacy
+                WHERE json_valid(legacy.data)
+                ORDER BY legacy.priority
+                "#,
+            ),
+            MigrationStep::DropColumn {
+                table: "cached_videos",
+                column: "video_data",
+            },
+            MigrationStep::DropColumn {
+                table: "favorites",
+                column: "video_data",
+            },
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_videos_channel ON videos(channel_title)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_videos_duration ON videos(duration)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_videos_ai_score ON videos(ai_score)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_search_history_videos_video ON search_history_videos(video_id)"),
+        ],
+    },
+    Migration {
+        version: 6,
+        description: "child profiles",
+        steps: &[
+            // 可为空的字段表示沿用全局设置
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS profiles (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    child_age TEXT,
+                    custom_filter_prompt TEXT,
+                    min_duration INTEGER,
+                    max_duration INTEGER,
+                    filter_mode TEXT,
+                    unverified_video_policy TEXT,
+                    is_active INTEGER NOT NULL DEFAULT 0,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL
+                )
+                "#,
+            ),
+            // 已有的收藏和历史归入默认档案，默认档案完全沿用全局设置
+            MigrationStep::Sql(
+                r#"
+                INSERT INTO profiles (id, name, is_active, created_at, updated_at)
+                SELECT 1, '默认', 1, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+                WHERE NOT EXISTS (SELECT 1 FROM profiles)
+                "#,
+            ),
+            // 收藏改为按档案唯一，需要重建表
+            MigrationStep::Sql(
+                r#"
+                INSERT OR IGNORE INTO videos (id, title, updated_at)
+                SELECT video_id, 'Unknown Video', created_at FROM favorites
+                "#,
+            ),
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE favorites_by_profile (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+                    video_id TEXT NOT NULL REFERENCES videos(id),
+                    user_notes TEXT,
+                    created_at TEXT NOT NULL,
+                    UNIQUE (profile_id, video_id)
+                )
+                "#,
+            ),
+            MigrationStep::Sql(
+                r#"
+                INSERT INTO favorites_by_profile (id, profile_id, video_id, user_notes, created_at)
+                SELECT id, 1, video_id, user_notes, created_at FROM favorites
+                "#,
+            ),
+            MigrationStep::Sql("DROP TABLE favorites"),
+            MigrationStep::Sql("ALTER TABLE favorites_by_profile RENAME TO favorites"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_favorites_profile ON favorites(profile_id, created_at)"),
+            // 开启外键约束时 ADD COLUMN 不能带非空默认值的 REFERENCES，删除档案时由代码清理
+            MigrationStep::AddColumn {
+                table: "search_history",
+                column: "profile_id",
+                definition: "INTEGER NOT NULL DEFAULT 1",
+            },
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_search_history_profile ON search_history(profile_id, created_at)"),
+            // 缓存的评分依赖年龄段，按档案区分；旧缓存没有档案，不再命中
+            MigrationStep::AddColumn {
+                table: "cached_videos",
+                column: "profile_id",
+                definition: "INTEGER",
+            },
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "screen time tracking",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "profiles",
+                column: "daily_screen_time_minutes",
+                definition: "INTEGER",
+            },
+            // 每次观看的开始和结束；ended_at 为空表示仍在观看（或应用异常退出）
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS watch_sessions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+                    video_id TEXT,
+                    started_at TEXT NOT NULL,
+                    last_heartbeat_at TEXT NOT NULL,
+                    ended_at TEXT,
+                    watched_seconds INTEGER NOT NULL DEFAULT 0
+                )
+                "#,
+            ),
+            // 每个档案每天（本地日期）的累计观看秒数，跨午夜的观看分别计入两天
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS screen_time_daily (
+                    profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+                    usage_date TEXT NOT NULL,
+                    watched_seconds INTEGER NOT NULL DEFAULT 0,
+                    PRIMARY KEY (profile_id, usage_date)
+                )
+                "#,
+            ),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_watch_sessions_profile ON watch_sessions(profile_id, started_at)"),
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "viewing schedules",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "profiles",
+                column: "timezone",
+                definition: "TEXT",
+            },
+            // 家长临时延长观看的截止时间（UTC）
+            MigrationStep::AddColumn {
+                table: "profiles",
+                column: "schedule_grace_until",
+                definition: "TEXT",
+            },
+            // weekdays 为逗号分隔的星期（1=周一 … 7=周日），时间为档案时区的 HH:MM
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS viewing_windows (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+                    weekdays TEXT NOT NULL,
+                    start_time TEXT NOT NULL,
+                    end_time TEXT NOT NULL,
+                    position INTEGER NOT NULL
+                )
+                "#,
+            ),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_viewing_windows_profile ON viewing_windows(profile_id, position)"),
+        ],
+    },
+    Migration {
+        version: 9,
+        description: "watch history",
+        steps: &[
+            // 同一次观看（档案、视频、开始时间相同）重复上报时更新同一行
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS watch_history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+                    video_id TEXT NOT NULL REFERENCES videos(id),
+                    started_at TEXT NOT NULL,
+                    watched_seconds INTEGER NOT NULL DEFAULT 0,
+                    completed INTEGER NOT NULL DEFAULT 0,
+                    updated_at TEXT NOT NULL,
+                    UNIQUE (profile_id, video_id, started_at)
+                )
+                "#,
+            ),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_watch_history_profile ON watch_history(profile_id, started_at)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_watch_history_video ON watch_history(video_id)"),
+        ],
+    },
+    Migration {
+        version: 10,
+        description: "search cache keyed per search",
+        steps: &[
+            // 同一视频可以出现在多个搜索的缓存中，主键包含完整的搜索条件
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE cached_videos_by_search (
+                    profile_id INTEGER NOT NULL,
+                    query TEXT NOT NULL,
+                    platform TEXT NOT NULL,
+                    filter_mode TEXT NOT NULL DEFAULT '',
+                    id TEXT NOT NULL REFERENCES videos(id),
+                    position INTEGER NOT NULL DEFAULT 0,
+                    cached_at TEXT NOT NULL,
+                    expires_at TEXT NOT NULL,
+                    PRIMARY KEY (profile_id, query, platform, filter_mode, id)
+                )
+                "#,
+            ),
+            // 没有档案的旧缓存本来就不会命中，直接丢弃
+            MigrationStep::Sql(
+                r#"
+                INSERT OR IGNORE INTO cached_videos_by_search (
+                    profile_id, query, platform, filter_mode, id, position, cached_at, expires_at
+                )
+                SELECT profile_id, query, platform, COALESCE(filter_mode, ''), id, rowid, cached_at, expires_at
+                FROM cached_videos
+                WHERE profile_id IS NOT NULL
+                "#,
+            ),
+            MigrationStep::Sql("DROP TABLE cached_videos"),
+            MigrationStep::Sql("ALTER TABLE cached_videos_by_search RENAME TO cached_videos"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_cached_videos_expires ON cached_videos(expires_at)"),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_cached_videos_video ON cached_videos(id)"),
+            // 每个缓存的搜索记录已加载到哪一页，命中缓存时返回下一页令牌
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS cached_searches (
+                    profile_id INTEGER NOT NULL,
+                    query TEXT NOT NULL,
+                    platform TEXT NOT NULL,
+                    filter_mode TEXT NOT NULL,
+                    next_page_token TEXT,
+                    cached_at TEXT NOT NULL,
+                    expires_at TEXT NOT NULL,
+                    PRIMARY KEY (profile_id, query, platform, filter_mode)
+                )
+                "#,
+            ),
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_cached_searches_expires ON cached_searches(expires_at)"),
+        ],
+    },
+    Migration {
+        version: 11,
+        description: "per-profile video scores",
+        steps: &[
+            // AI评分取决于档案的年龄段和提示词，按档案分别保存；videos 表只保留元数据
+            MigrationStep::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS profile_video_scores (
+                    profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+                    video_id TEXT NOT NULL,
+                    ai_score REAL,
+                    education_score REAL,
+                    safety_score REAL,
+                    age_appropriate INTEGER,
+                    analysis_status TEXT,
+                    ai_reasoning TEXT,
+                    recommended_age TEXT,
+                    age_fit_score REAL,
+                    quality_score REAL,
+                    updated_at TEXT NOT NULL,
+                    PRIMARY KEY (profile_id, video_id)
+                )
+                "#,
+            ),
+            // 原有的全局评分无法区分来源，复制给所有引用该视频的档案，下次搜索时会被重新评分覆盖
+            MigrationStep::Sql(
+                r#"
+                INSERT OR IGNORE INTO profile_video_scores (
+                    profile_id, video_id, ai_score, education_score, safety_score, age_appropriate,
+                    analysis_status, ai_reasoning, recommended_age, age_fit_score, quality_score, updated_at
+                )
+                SELECT
+                    refs.profile_id, v.id, v.ai_score, v.education_score, v.safety_score, v.age_appropriate,
+                    v.analysis_status, v.ai_reasoning, v.recommended_age, v.age_fit_score, v.quality_score, v.updated_at
+                FROM videos v
+                JOIN (
+                    SELECT profile_id, id AS video_id FROM cached_videos
+                    UNION
+                    SELECT profile_id, video_id FROM favorites
+                    UNION
+                    SELECT profile_id, video_id FROM watch_history
+                    UNION
+                    SELECT h.profile_id, hv.video_id
+                    FROM search_history_videos hv
+                    JOIN search_history h ON h.id = hv.history_id
+                ) AS refs ON refs.video_id = v.id
+                JOIN profiles p ON p.id = refs.profile_id
+                WHERE v.analysis_status IS NOT NULL OR v.ai_score IS NOT NULL
+                "#,
+            ),
+            MigrationStep::Sql("DROP INDEX IF EXISTS idx_videos_ai_score"),
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "ai_score",
+            },
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "education_score",
+            },
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "safety_score",
+            },
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "age_appropriate",
+            },
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "analysis_status",
+            },
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "ai_reasoning",
+            },
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "recommended_age",
+            },
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "age_fit_score",
+            },
+            MigrationStep::DropColumn {
+                table: "videos",
+                column: "quality_score",
+            },
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS idx_profile_video_scores_video ON profile_video_scores(video_id)"),
+        ],
+    },
+];
+
+// 数据库迁移 - 按 schema_version 记录的版本依次执行未应用的迁移
+async fn run_migrations(pool: &SqlitePool, db_path: &Path) -> Result<()> {
     println!("🔄 Running database migrations...");
-    
-    // 创建视频缓存表
+
     sqlx::query(
         r#"
-        CREATE TABLE IF NOT EXISTS cached_videos (
-            id TEXT PRIMARY KEY,
-            query TEXT NOT NULL,
-            platform TEXT NOT NULL,
-            video_data TEXT NOT NULL,
-            cached_at TEXT NOT NULL,
-            expires_at TEXT NOT NULL
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
         )
         "#
     )
     .execute(pool)
     .await?;
-    
-    // 创建收藏表
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS favorites (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            video_id TEXT NOT NULL UNIQUE,
-            user_notes TEXT,
-            created_at TEXT NOT NULL,
-            video_data TEXT NOT NULL
-        )
-        "#
+
+    let (current_version,): (i64,) = sqlx::query_as("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(pool)
+        .await?;
+
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current_version)
+        .collect();
+
+    if pending.is_empty() {
+        println!("✅ Database schema is up to date (version {})", current_version);
+        return Ok(());
+    }
+
+    // 已有数据的数据库（包括没有版本记录的旧安装）升级前先备份
+    if has_user_tables(pool).await? {
+        let backup_path = backup_database(pool, db_path, current_version).await?;
+        println!("💾 Database backed up to {}", backup_path.display());
+    }
+
+    for migration in pending {
+        println!("🔧 Applying migration v{}: {}", migration.version, migration.description);
+
+        // 每个版本在独立事务中执行，失败时不会留下半完成的结构
+        let mut tx = pool.begin().await?;
+        for step in migration.steps {
+            match step {
+                MigrationStep::Sql(sql) => {
+                    sqlx::query(sql).execute(&mut *tx).await?;
+                }
+                MigrationStep::AddColumn { table, column, definition } => {
+                    add_column_if_missing(&mut tx, table, column, definition).await?;
+                }
+                MigrationStep::DropColumn { table, column } => {
+                    drop_column_if_exists(&mut tx, table, column).await?;
+                }
+            }
+        }
+        sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(chrono::Utc::now().to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit()
+            .await
+            .map_err(|e| anyhow::anyhow!("Migration v{} failed: {}", migration.version, e))?;
+    }
+
+    println!("✅ Database migrations completed successfully");
+    Ok(())
+}
+
+// 除 schema_version 以外是否已存在业务表
+async fn has_user_tables(pool: &SqlitePool) -> Result<bool> {
+    let (count,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name != 'schema_version'"
     )
-    .execute(pool)
+    .fetch_one(pool)
     .await?;
-    
-    // 创建搜索历史表
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS search_history (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            query TEXT NOT NULL,
-            platform TEXT NOT NULL,
-            filter_mode TEXT NOT NULL,
-            results_count INTEGER NOT NULL,
-            created_at TEXT NOT NULL
-        )
-        "#
-    )
-    .execute(pool)
-    .await?;
-    
-    // 创建使用统计表
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS usage_stats (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            event_type TEXT NOT NULL,
-            event_data TEXT,
-            created_at TEXT NOT NULL
-        )
-        "#
-    )
-    .execute(pool)
-    .await?;
-    
-    // 创建索引
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_cached_videos_query ON cached_videos(query, platform)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_cached_videos_expires ON cached_videos(expires_at)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_search_history_date ON search_history(created_at)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_usage_stats_date ON usage_stats(created_at)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_favorites_created ON favorites(created_at)")
+    Ok(count > 0)
+}
+
+// 使用 VACUUM INTO 生成一致的数据库快照，保存在 backups 目录下
+async fn backup_database(pool: &SqlitePool, db_path: &Path, from_version: i64) -> Result<PathBuf> {
+    let backup_dir = db_path
+        .parent()
+        .map(|dir| dir.join("backups"))
+        .ok_or_else(|| anyhow::anyhow!("Invalid database path: {}", db_path.display()))?;
+    std::fs::create_dir_all(&backup_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create backup directory: {}", e))?;
+
+    let backup_path = backup_dir.join(format!(
+        "app-v{}-{}.db",
+        from_version,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    sqlx::query("VACUUM INTO ?")
+        .bind(backup_path.to_string_lossy().to_string())
         .execute(pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to back up database: {}", e))?;
+
+    Ok(backup_path)
+}
+
+async fn column_exists(conn: &mut SqliteConnection, table: &str, column: &str) -> Result<bool> {
+    let (exists,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+        .bind(table)
+        .bind(column)
+        .fetch_one(&mut *conn)
         .await?;
-    
-    println!("✅ Database migrations completed successfully");
+    Ok(exists > 0)
+}
+
+// SQLite 不支持 ADD COLUMN IF NOT EXISTS，先检查列是否已存在
+async fn add_column_if_missing(conn: &mut SqliteConnection, table: &str, column: &str, definition: &str) -> Result<()> {
+    if !column_exists(conn, table, column).await? {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(&mut *conn)
+            .await?;
+        println!("🔧 Added column {}.{}", table, column);
+    }
+    Ok(())
+}
+
+async fn drop_column_if_exists(conn: &mut SqliteConnection, table: &str, column: &str) -> Result<()> {
+    if column_exists(conn, table, column).await? {
+        sqlx::query(&format!("ALTER TABLE {} DROP COLUMN {}", table, column))
+            .execute(&mut *conn)
+            .await?;
+        println!("🔧 Dropped column {}.{}", table, column);
+    }
     Ok(())
 }
 
+// 家长PIN：以 Argon2 加盐哈希（PHC 字符串，内含盐值）保存在应用数据目录
+// 验证成功后发放短期解锁令牌，特权命令需要携带该令牌
+const PARENT_SESSION_TTL_SECS: u64 = 10 * 60;
+const PARENT_PIN_MAX_ATTEMPTS: u32 = 5; // 连续输错次数上限，超过后暂时锁定
+const PARENT_PIN_LOCKOUT_SECS: u64 = 60; // 首次锁定时长，之后每次输错翻倍
+const PARENT_PIN_MAX_LOCKOUT_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredParentPin {
+    hash: String,
+    created_at: String,
+}
+
+struct ParentSession {
+    token: String,
+    expires_at: std::time::Instant,
+}
+
+#[derive(Default)]
+struct ParentLock {
+    session: Option<ParentSession>,
+    failed_attempts: u32,
+    locked_until: Option<std::time::Instant>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParentUnlock {
+    pub token: String,
+    pub expires_in_seconds: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParentPinStatus {
+    pub pin_set: bool,
+    pub unlocked: bool, // 传入的令牌是否仍然有效
+    pub lockout_seconds: u64, // 输错次数过多时剩余的锁定秒数
+}
+
+fn load_parent_pin(app_state: &AppState) -> Result<Option<StoredParentPin>> {
+    let path = app_state.parent_pin_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+fn hash_parent_pin(pin: &str) -> Result<String> {
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    use rand::rngs::OsRng;
+
+    let salt = SaltString::generate(&mut OsRng);
+    argon2::Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to hash parent PIN: {}", e))
+}
+
+fn verify_parent_pin(stored: &StoredParentPin, pin: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+    PasswordHash::new(&stored.hash)
+        .map(|hash| argon2::Argon2::default().verify_password(pin.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+fn generate_session_token() -> String {
+    use rand::{rngs::OsRng, RngCore};
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl ParentLock {
+    fn session_valid(&self, token: Option<&str>) -> bool {
+        match (&self.session, token) {
+            (Some(session), Some(token)) => {
+                session.token == token && session.expires_at > std::time::Instant::now()
+            }
+            _ => false,
+        }
+    }
+
+    fn lockout_remaining(&self) -> u64 {
+        self.locked_until
+            .and_then(|until| until.checked_duration_since(std::time::Instant::now()))
+            .map(|remaining| remaining.as_secs() + 1)
+            .unwrap_or(0)
+    }
+
+    // 记录一次输错，返回因此锁定的秒数（未达到上限时为 0）
+    // 连续输错次数只在验证成功后清零，锁定结束后再次输错会锁定更久
+    fn record_failure(&mut self) -> u64 {
+        self.failed_attempts += 1;
+        if self.failed_attempts < PARENT_PIN_MAX_ATTEMPTS {
+            return 0;
+        }
+        let lockouts = (self.failed_attempts - PARENT_PIN_MAX_ATTEMPTS).min(16);
+        let lockout_secs = PARENT_PIN_LOCKOUT_SECS
+            .saturating_mul(1 << lockouts)
+            .min(PARENT_PIN_MAX_LOCKOUT_SECS);
+        self.locked_until = Some(std::time::Instant::now() + std::time::Duration::from_secs(lockout_secs));
+        lockout_secs
+    }
+
+    // 验证成功：清零输错次数并开始新的解锁会话，返回会话令牌
+    fn record_success(&mut self) -> String {
+        let token = generate_session_token();
+        self.failed_attempts = 0;
+        self.locked_until = None;
+        self.session = Some(ParentSession {
+            token: token.clone(),
+            expires_at: std::time::Instant::now() + std::time::Duration::from_secs(PARENT_SESSION_TTL_SECS),
+        });
+        token
+    }
+}
+
+// 特权命令的检查：未设置PIN时保持开放，设置后必须携带有效的解锁令牌
+fn require_parent(app_state: &AppState, token: Option<&str>) -> Result<(), AppError> {
+    let pin = load_parent_pin(app_state)
+        .map_err(|e| AppError::Internal(format!("Failed to read parent PIN: {}", e)))?;
+    if pin.is_none() {
+        return Ok(());
+    }
+
+    let lock = app_state.parent_lock.lock().unwrap_or_else(|e| e.into_inner());
+    if lock.session_valid(token) {
+        Ok(())
+    } else {
+        Err(AppError::ParentLocked("Parent PIN is required for this action".to_string()))
+    }
+}
+
 // 配置管理函数
 async fn load_settings_from_file(app_state: &AppState) -> AppSettings {
     let config_path = app_state.config_file_path();
@@ -540,8 +2012,12 @@ async fn load_settings_from_file(app_state: &AppState) -> AppSettings {
         match tokio::fs::read_to_string(&config_path).await {
             Ok(content) => {
                 match serde_json::from_str::<AppSettings>(&content) {
-                    Ok(settings) => {
+                    Ok(mut settings) => {
                         println!("✅ Settings loaded from file: {:?}", config_path);
+                        // 未修改过的旧版默认提示词升级为按年龄渲染的模板
+                        if settings.custom_filter_prompt.as_deref() == Some(LEGACY_DEFAULT_FILTER_PROMPT) {
+                            settings.custom_filter_prompt = Some(default_filter_prompt());
+                        }
                         return settings;
                     }
                     Err(e) => {
@@ -648,31 +2124,105 @@ fn extract_video_id_from_input(input: &str) -> Option<String> {
     None
 }
 
+// YouTube Data API 各接口的配额消耗（单位）
+const SEARCH_LIST_QUOTA_COST: i64 = 100;
+const VIDEOS_LIST_QUOTA_COST: i64 = 1;
+const CAPTIONS_LIST_QUOTA_COST: i64 = 50;
+
+// YouTube 每日配额在太平洋时间午夜重置（含夏令时）
+fn quota_date(clock: &dyn Clock) -> String {
+    clock
+        .now()
+        .with_timezone(&chrono_tz::America::Los_Angeles)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+// 调用YouTube API所需的客户端、密钥，以及记录配额消耗的数据库
+// units_used 累计本次操作实际消耗的配额，用于控制单次搜索的配额预算
+#[derive(Clone, Copy)]
+struct YouTubeApi<'a> {
+    client: &'a Client,
+    api_key: &'a str,
+    db: &'a SqlitePool,
+    clock: &'a dyn Clock,
+    units_used: &'a std::sync::atomic::AtomicI64,
+}
+
+impl YouTubeApi<'_> {
+    // 请求一旦发出就会计入配额（包括返回错误的请求），记录失败不影响调用本身
+    async fn record_quota(&self, endpoint: &str, units: i64) {
+        self.units_used.fetch_add(units, std::sync::atomic::Ordering::Relaxed);
+        if let Err(e) = record_quota_usage(self.db, self.clock, endpoint, units).await {
+            println!("⚠️ Failed to record YouTube quota usage: {}", e);
+        }
+    }
+}
+
+async fn record_quota_usage(pool: &SqlitePool, clock: &dyn Clock, endpoint: &str, units: i64) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO youtube_quota_usage (quota_date, endpoint, units, created_at) VALUES (?, ?, ?, ?)"
+    )
+    .bind(quota_date(clock))
+    .bind(endpoint)
+    .bind(units)
+    .bind(clock.now().to_rfc3339())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// 当日配额是否已达到预算；读取失败时不阻止搜索
+async fn youtube_quota_exhausted(pool: &SqlitePool, clock: &dyn Clock, daily_quota: i64) -> bool {
+    match quota_used_today(pool, clock).await {
+        Ok(used) => used >= daily_quota,
+        Err(e) => {
+            println!("⚠️ Failed to read YouTube quota usage: {}", e);
+            false
+        }
+    }
+}
+
+// 当日已消耗的配额
+async fn quota_used_today(pool: &SqlitePool, clock: &dyn Clock) -> Result<i64> {
+    let (used,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(units), 0) FROM youtube_quota_usage WHERE quota_date = ?"
+    )
+    .bind(quota_date(clock))
+    .fetch_one(pool)
+    .await?;
+    Ok(used)
+}
+
 // 根据 video ID 直接获取视频详细信息
 // 使用 videos.list API，配额消耗仅为 1 单位（相比 search.list 的 100 单位）
 async fn get_video_by_id(
-    client: &Client,
-    api_key: &str,
+    api: YouTubeApi<'_>,
     video_id: &str,
 ) -> Result<Vec<Video>> {
     println!("🎯 Fetching video by ID: {}", video_id);
 
     // 使用 videos.list API 获取视频详细信息
     let details_url = format!(
-        "https://www.googleapis.com/youtube/v3/videos?part=snippet,contentDetails,statistics&id={}&key={}",
+        "https://www.googleapis.com/youtube/v3/videos?part={}&id={}&key={}",
+        VIDEO_DETAIL_PARTS,
         video_id,
-        api_key
+        api.api_key
     );
 
-    let response: YouTubeVideoDetailsResponse = client
+    let response = api.client
         .get(&details_url)
         .timeout(tokio::time::Duration::from_secs(10))
         .send()
         .await
-        .map_err(|e| anyhow::anyhow!("YouTube video details request failed: {}", e))?
+        .map_err(|e| AppError::Network(format!("YouTube video details request failed: {}", e)))?;
+    api.record_quota("videos.list", VIDEOS_LIST_QUOTA_COST).await;
+    let response = check_youtube_response(response, "videos.list").await?;
+
+    let response: YouTubeVideoDetailsResponse = response
         .json()
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to parse YouTube video details: {}", e))?;
+        .map_err(|e| AppError::ProviderParse(format!("Failed to parse YouTube video details: {}", e)))?;
 
     if response.items.is_empty() {
         return Err(anyhow::anyhow!("Video with ID '{}' not found or unavailable", video_id));
@@ -681,7 +2231,7 @@ async fn get_video_by_id(
     println!("✅ Found video by ID, fetching additional details...");
 
     // 获取字幕信息
-    let caption = fetch_video_captions(client, api_key, video_id).await;
+    let caption = fetch_video_captions(api, video_id).await;
 
     // 转换为 Video 对象
     let mut videos = Vec::new();
@@ -735,7 +2285,7 @@ async fn get_video_by_id(
             .and_then(|s| s.like_count.as_ref())
             .and_then(|l| l.parse().ok());
 
-        let video = Video {
+        let mut video = Video {
             id: video_id_str,
             title,
             description,
@@ -752,7 +2302,9 @@ async fn get_video_by_id(
             tags: None,
             cached_at: Some(chrono::Utc::now().to_rfc3339()),
             subtitles: caption.clone(),
+            ..Default::default()
         };
+        item.apply_signals_to(&mut video);
 
         videos.push(video);
     }
@@ -761,19 +2313,274 @@ async fn get_video_by_id(
     Ok(videos)
 }
 
+// 每页搜索结果数量
+const SEARCH_PAGE_SIZE: i32 = 20;
+// 每页消耗配额的预估：search.list 100 单位 + videos.list 1 单位 + 每个视频的 captions.list 50 单位
+// 只用于默认预算和第一页之前的判断，之后按实际记录的消耗计算
+const SEARCH_PAGE_QUOTA_COST: i32 =
+    (SEARCH_LIST_QUOTA_COST + VIDEOS_LIST_QUOTA_COST + SEARCH_PAGE_SIZE as i64 * CAPTIONS_LIST_QUOTA_COST) as i32;
+
+// 视频平台
+// YouTube Kids 没有独立的公开API，通过 search.list 搜索后用 status.madeForKids 过滤
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Platform {
+    YouTube,
+    YouTubeKids,
+}
+
+impl Platform {
+    fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "youtube" => Some(Platform::YouTube),
+            "youtube_kids" => Some(Platform::YouTubeKids),
+            _ => None,
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Platform::YouTube => "youtube",
+            Platform::YouTubeKids => "youtube_kids",
+        }
+    }
+
+    // 儿童模式只保留明确标记为“面向儿童”的视频
+    fn accepts(&self, video: &Video) -> bool {
+        match self {
+            Platform::YouTube => true,
+            Platform::YouTubeKids => video.made_for_kids == Some(true),
+        }
+    }
+
+    // 标记平台并过滤掉该平台不接受的视频
+    fn apply(&self, videos: Vec<Video>) -> Vec<Video> {
+        let total = videos.len();
+        let accepted: Vec<Video> = videos
+            .into_iter()
+            .filter(|video| self.accepts(video))
+            .map(|mut video| {
+                video.platform = Some(self.id().to_string());
+                video
+            })
+            .collect();
+
+        if accepted.len() < total {
+            println!("🧒 {} filtered out {} videos not made for kids", self.id(), total - accepted.len());
+        }
+        accepted
+    }
+}
+
+// 时长范围（分钟），在搜索和AI分析之前确定性地过滤视频
+#[derive(Debug, Clone, Copy)]
+struct DurationBounds {
+    min_minutes: i32,
+    max_minutes: i32,
+}
+
+impl DurationBounds {
+    fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            min_minutes: settings.min_duration,
+            max_minutes: settings.max_duration,
+        }
+    }
+
+    fn contains(&self, duration_secs: i32) -> bool {
+        duration_secs >= self.min_minutes * 60 && duration_secs <= self.max_minutes * 60
+    }
+
+    // 映射到 search.list 的 videoDuration：short（4分钟以内）、medium（4-20分钟）、long（20分钟以上）
+    // 范围跨越多个区间时不限制，由获取详情后的过滤保证
+    fn video_duration_bucket(&self) -> Option<&'static str> {
+        if self.max_minutes <= 4 {
+            Some("short")
+        } else if self.min_minutes >= 4 && self.max_minutes <= 20 {
+            Some("medium")
+        } else if self.min_minutes >= 20 {
+            Some("long")
+        } else {
+            None
+        }
+    }
+}
+
+// 时长未知或不在范围内的视频不发送给AI
+fn filter_videos_by_duration(videos: Vec<Video>, bounds: DurationBounds) -> Vec<Video> {
+    let total = videos.len();
+    let kept: Vec<Video> = videos
+        .into_iter()
+        .filter(|video| video.duration.is_some_and(|duration| bounds.contains(duration)))
+        .collect();
+
+    if kept.len() < total {
+        println!(
+            "⏱️ Duration filter dropped {} videos outside {}-{} minutes",
+            total - kept.len(),
+            bounds.min_minutes,
+            bounds.max_minutes
+        );
+    }
+    kept
+}
+
+// 一次 search.list 请求的参数
+#[derive(Debug, Clone, Copy)]
+struct YouTubeSearchParams<'a> {
+    platform: Platform,
+    duration: DurationBounds,
+    query: &'a str,
+    max_results: i32,
+    page_token: Option<&'a str>,
+    language: &'a str,
+    region: &'a str,
+}
+
+// 搜索语言与地区；search_language 为 "both" 时中英文分别搜索后合并
+#[derive(Debug, Clone)]
+struct SearchLocale {
+    languages: Vec<String>,
+    region: String,
+}
+
+impl SearchLocale {
+    fn from_settings(settings: &AppSettings) -> Self {
+        let languages = match settings.search_language.as_str() {
+            "both" => vec!["zh".to_string(), "en".to_string()],
+            language => vec![language.to_string()],
+        };
+        Self {
+            languages,
+            region: settings.search_region.trim().to_uppercase(),
+        }
+    }
+
+    // 单一语言时直接使用YouTube的令牌，多语言时为 {语言: 令牌} 的JSON
+    // 返回 None 表示从第一页开始搜索
+    fn decode_page_token(&self, token: Option<&str>) -> Option<HashMap<String, String>> {
+        let token = token.filter(|token| !token.is_empty())?;
+        if let [language] = self.languages.as_slice() {
+            return Some(HashMap::from([(language.clone(), token.to_string())]));
+        }
+        match serde_json::from_str(token) {
+            Ok(tokens) => Some(tokens),
+            Err(e) => {
+                println!("⚠️ Ignoring invalid page token, starting from the first page: {}", e);
+                None
+            }
+        }
+    }
+
+    fn encode_page_token(&self, tokens: &HashMap<String, String>) -> Option<String> {
+        if tokens.is_empty() {
+            return None;
+        }
+        if self.languages.len() == 1 {
+            return tokens.values().next().cloned();
+        }
+        serde_json::to_string(tokens).ok()
+    }
+}
+
+// 按语言并行搜索一页并合并，结果按语言交替排列并按视频ID去重
+// 返回合并后的结果、各语言的下一页令牌以及实际发出的请求数
+async fn search_youtube_locales(
+    api: YouTubeApi<'_>,
+    platform: Platform,
+    duration: DurationBounds,
+    query: &str,
+    locale: &SearchLocale,
+    page_tokens: Option<&HashMap<String, String>>,
+) -> Result<(Vec<Video>, HashMap<String, String>, i32)> {
+    // 第一页搜索所有语言，之后只继续还有下一页的语言
+    let requests: Vec<(&str, Option<&str>)> = match page_tokens {
+        None => locale.languages.iter().map(|language| (language.as_str(), None)).collect(),
+        Some(tokens) => locale
+            .languages
+            .iter()
+            .filter_map(|language| {
+                tokens.get(language).map(|token| (language.as_str(), Some(token.as_str())))
+            })
+            .collect(),
+    };
+
+    let search_futures = requests.iter().map(|(language, page_token)| {
+        let params = YouTubeSearchParams {
+            platform,
+            duration,
+            query,
+            max_results: SEARCH_PAGE_SIZE,
+            page_token: *page_token,
+            language,
+            region: &locale.region,
+        };
+        search_youtube_videos_with_retry(api, params, 3)
+    });
+    let results = futures::future::join_all(search_futures).await;
+
+    let mut pages = Vec::new();
+    let mut next_tokens = HashMap::new();
+    let mut last_error = None;
+    for ((language, _), result) in requests.iter().zip(results) {
+        match result {
+            Ok(page) => {
+                if let Some(token) = page.next_page_token {
+                    next_tokens.insert(language.to_string(), token);
+                }
+                pages.push(page.videos);
+            }
+            Err(e) => {
+                println!("⚠️ YouTube search in '{}' failed: {}", language, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    // 所有语言都失败时才视为搜索失败
+    if pages.is_empty() {
+        if let Some(e) = last_error {
+            return Err(e);
+        }
+    }
+
+    Ok((merge_locale_pages(pages), next_tokens, requests.len() as i32))
+}
+
+// 各语言的结果交替排列，同一视频只保留第一次出现的位置
+fn merge_locale_pages(pages: Vec<Vec<Video>>) -> Vec<Video> {
+    let mut pages: Vec<_> = pages.into_iter().map(Vec::into_iter).collect();
+    let mut seen_ids = std::collections::HashSet::new();
+    let mut merged = Vec::new();
+    loop {
+        let mut exhausted = true;
+        for page in pages.iter_mut() {
+            if let Some(video) = page.next() {
+                exhausted = false;
+                if seen_ids.insert(video.id.clone()) {
+                    merged.push(video);
+                }
+            }
+        }
+        if exhausted {
+            break;
+        }
+    }
+    merged
+}
+
 // API辅助函数 - 参考Chrome扩展的实现模式
 async fn search_youtube_videos_with_retry(
-    client: &Client,
-    query: &str,
-    api_key: &str,
-    max_results: i32,
+    api: YouTubeApi<'_>,
+    params: YouTubeSearchParams<'_>,
     max_retries: u32,
-) -> Result<Vec<Video>> {
+) -> Result<YouTubeSearchPage> {
     let mut last_error = None;
     
     for attempt in 1..=max_retries {
-        match search_youtube_videos(client, query, api_key, max_results).await {
+        match search_youtube_videos(api, params).await {
             Ok(videos) => return Ok(videos),
+            // 配额耗尽或密钥无效时重试没有意义
+            Err(e) if AppError::is_quota_or_key_error(&e) => return Err(e),
             Err(e) => {
                 last_error = Some(e);
                 println!("🔄 YouTube search attempt {} failed, retrying...", attempt);
@@ -791,40 +2598,60 @@ async fn search_youtube_videos_with_retry(
 }
 
 async fn search_youtube_videos(
-    client: &Client,
-    query: &str,
-    api_key: &str,
-    max_results: i32,
-) -> Result<Vec<Video>> {
-    println!("🔍 Searching YouTube with API: query='{}', maxResults={}", query, max_results);
+    api: YouTubeApi<'_>,
+    params: YouTubeSearchParams<'_>,
+) -> Result<YouTubeSearchPage> {
+    let query = params.query;
+    println!(
+        "🔍 Searching {} with API: query='{}', maxResults={}, language={}, region={}, pageToken={:?}",
+        params.platform.id(), query, params.max_results, params.language, params.region, params.page_token
+    );
 
     // ✅ 检测是否为 video ID 或包含 video ID 的 URL
     if let Some(video_id) = extract_video_id_from_input(query) {
         println!("🎯 Query is a video ID, fetching directly (saves API quota: 1 vs 100 units)");
-        return get_video_by_id(client, api_key, &video_id).await;
+        let videos = get_video_by_id(api, &video_id).await?;
+        return Ok(YouTubeSearchPage {
+            videos: params.platform.apply(videos),
+            next_page_token: None,
+        });
     }
 
     // 构建搜索参数 - 参考Chrome扩展的参数设置
-    let search_url = format!(
-        "https://www.googleapis.com/youtube/v3/search?part=snippet&type=video&q={}&maxResults={}&key={}&order=relevance&safeSearch=strict&videoCategoryId=22&videoEmbeddable=true&relevanceLanguage=en&regionCode=US",
+    let mut search_url = format!(
+        "https://www.googleapis.com/youtube/v3/search?part=snippet&type=video&q={}&maxResults={}&key={}&order=relevance&safeSearch=strict&videoCategoryId=22&videoEmbeddable=true&relevanceLanguage={}",
         urlencoding::encode(query),
-        std::cmp::min(max_results, 50),
-        api_key
+        std::cmp::min(params.max_results, 50),
+        api.api_key,
+        urlencoding::encode(params.language)
     );
+    if !params.region.is_empty() {
+        search_url.push_str(&format!("&regionCode={}", urlencoding::encode(params.region)));
+    }
+    if let Some(bucket) = params.duration.video_duration_bucket() {
+        search_url.push_str(&format!("&videoDuration={}", bucket));
+    }
+    if let Some(token) = params.page_token {
+        search_url.push_str(&format!("&pageToken={}", urlencoding::encode(token)));
+    }
 
-    let search_response: YouTubeSearchResponse = client
+    let response = api.client
         .get(&search_url)
         .timeout(tokio::time::Duration::from_secs(10))
         .send()
         .await
-        .map_err(|e| anyhow::anyhow!("YouTube search request failed: {}", e))?
+        .map_err(|e| AppError::Network(format!("YouTube search request failed: {}", e)))?;
+    api.record_quota("search.list", SEARCH_LIST_QUOTA_COST).await;
+    let response = check_youtube_response(response, "search.list").await?;
+
+    let search_response: YouTubeSearchResponse = response
         .json()
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to parse YouTube search response: {}", e))?;
+        .map_err(|e| AppError::ProviderParse(format!("Failed to parse YouTube search response: {}", e)))?;
 
     if search_response.items.is_empty() {
         println!("⚠️ No videos found for query: {}", query);
-        return Ok(vec![]);
+        return Ok(YouTubeSearchPage::default());
     }
     
     println!("📊 YouTube API returned {} videos (total: {})", 
@@ -840,30 +2667,35 @@ async fn search_youtube_videos(
     println!("📊 Found {} videos, getting detailed information...", video_ids.len());
 
     // 批量获取视频详细信息 - 参考Chrome扩展的getMultipleVideoDetails
-    let detailed_videos = get_multiple_video_details(client, api_key, &video_ids).await?;
-    
-    // 批量获取字幕信息
-    println!("📝 Fetching subtitle information for videos...");
-    let captions = fetch_multiple_video_captions(client, api_key, &video_ids).await;
+    let detailed_videos = get_multiple_video_details(api, &video_ids).await?;
     
     // 格式化视频数据 - 参考Chrome扩展的formatVideoData
-    let mut formatted_videos = Vec::new();
-    
-    for ((search_item, details_opt), caption_opt) in search_response.items.iter()
+    let formatted_videos: Vec<Video> = search_response.items.iter()
         .zip(detailed_videos.iter())
-        .zip(captions.iter()) {
-        let mut formatted = format_video_data(search_item, details_opt.as_ref());
-        formatted.subtitles = caption_opt.clone();
-        formatted_videos.push(formatted);
+        .map(|(search_item, details_opt)| format_video_data(search_item, details_opt.as_ref()))
+        .collect();
+
+    // 字幕请求每个视频消耗50单位，先用平台、预筛选和时长条件过滤，只为留下的视频获取字幕
+    let mut videos = filter_videos_by_duration(
+        prefilter_videos(params.platform.apply(formatted_videos)),
+        params.duration,
+    );
+    println!("📝 Fetching subtitle information for {} videos...", videos.len());
+    let kept_ids: Vec<String> = videos.iter().map(|video| video.id.clone()).collect();
+    let captions = fetch_multiple_video_captions(api, &kept_ids).await;
+    for (video, caption) in videos.iter_mut().zip(captions) {
+        video.subtitles = caption;
     }
 
-    Ok(formatted_videos)
+    Ok(YouTubeSearchPage {
+        videos,
+        next_page_token: search_response.next_page_token,
+    })
 }
 
 // 批量获取视频详细信息 - 参考Chrome扩展实现
 async fn get_multiple_video_details(
-    client: &Client,
-    api_key: &str,
+    api: YouTubeApi<'_>,
     video_ids: &[String],
 ) -> Result<Vec<Option<YouTubeVideoDetail>>> {
     if video_ids.is_empty() {
@@ -871,20 +2703,25 @@ async fn get_multiple_video_details(
     }
 
     let details_url = format!(
-        "https://www.googleapis.com/youtube/v3/videos?part=snippet,contentDetails,statistics&id={}&key={}",
+        "https://www.googleapis.com/youtube/v3/videos?part={}&id={}&key={}",
+        VIDEO_DETAIL_PARTS,
         video_ids.join(","),
-        api_key
+        api.api_key
     );
 
-    let details_response: YouTubeVideoDetailsResponse = client
+    let response = api.client
         .get(&details_url)
         .timeout(tokio::time::Duration::from_secs(10))
         .send()
         .await
-        .map_err(|e| anyhow::anyhow!("YouTube details request failed: {}", e))?
+        .map_err(|e| AppError::Network(format!("YouTube details request failed: {}", e)))?;
+    api.record_quota("videos.list", VIDEOS_LIST_QUOTA_COST).await;
+    let response = check_youtube_response(response, "videos.list").await?;
+
+    let details_response: YouTubeVideoDetailsResponse = response
         .json()
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to parse YouTube details response: {}", e))?;
+        .map_err(|e| AppError::ProviderParse(format!("Failed to parse YouTube details response: {}", e)))?;
 
     // 创建一个映射来匹配video_id和详细信息
     let mut details_map: std::collections::HashMap<String, YouTubeVideoDetail> = 
@@ -907,24 +2744,24 @@ async fn get_multiple_video_details(
 
 // 获取视频字幕信息
 async fn fetch_video_captions(
-    client: &Client,
-    api_key: &str,
+    api: YouTubeApi<'_>,
     video_id: &str,
 ) -> Option<String> {
     // 使用YouTube Data API v3的captions endpoint
     let captions_url = format!(
         "https://www.googleapis.com/youtube/v3/captions?part=snippet&videoId={}&key={}",
         video_id,
-        api_key
+        api.api_key
     );
 
-    match client
+    match api.client
         .get(&captions_url)
         .timeout(tokio::time::Duration::from_secs(5))
         .send()
         .await
     {
         Ok(response) => {
+            api.record_quota("captions.list", CAPTIONS_LIST_QUOTA_COST).await;
             if let Ok(caption_response) = response.json::<YouTubeCaptionResponse>().await {
                 // 查找中文或英文字幕
                 let captions_info: Vec<String> = caption_response.items
@@ -957,21 +2794,12 @@ async fn fetch_video_captions(
 
 // 批量获取视频字幕信息
 async fn fetch_multiple_video_captions(
-    client: &Client,
-    api_key: &str,
+    api: YouTubeApi<'_>,
     video_ids: &[String],
 ) -> Vec<Option<String>> {
-    let mut caption_futures = Vec::new();
-    
-    for video_id in video_ids {
-        let client_clone = client.clone();
-        let api_key_clone = api_key.to_string();
-        let video_id_clone = video_id.clone();
-        
-        caption_futures.push(async move {
-            fetch_video_captions(&client_clone, &api_key_clone, &video_id_clone).await
-        });
-    }
+    let caption_futures = video_ids
+        .iter()
+        .map(|video_id| fetch_video_captions(api, video_id));
     
     futures::future::join_all(caption_futures).await
 }
@@ -1005,7 +2833,7 @@ fn format_video_data(search_item: &YouTubeVideoItem, details: Option<&YouTubeVid
         (None, None, None)
     };
 
-    Video {
+    let mut video = Video {
         id: search_item.id.video_id.clone(),
         title: snippet.title.clone(),
         description: Some(snippet.description.clone()),
@@ -1022,165 +2850,588 @@ fn format_video_data(search_item: &YouTubeVideoItem, details: Option<&YouTubeVid
         age_appropriate: None,
         tags: None,
         subtitles: None,  // It will be filled in later
+        ..Default::default()
+    };
+    if let Some(details) = details {
+        details.apply_signals_to(&mut video);
     }
+    video
 }
 
-async fn analyze_with_openai(
-    client: &Client,
-    api_key: &str,
-    title: &str,
-    description: &str,
-    custom_prompt: Option<&str>,
-) -> Result<AIAnalysisResponse> {
-    let prompt = custom_prompt.unwrap_or(
-        "请分析这个视频对儿童的适宜性。请提供：\n\
-        1. 教育价值评分(0-1)\n\
-        2. 安全性评分(0-1)\n\
-        3. 是否适合儿童(true/false)\n\
-        4. 综合评分(0-1)\n\
-        5. 推荐年龄段\n\
-        6. 详细理由\n\n\
-        请以JSON格式返回：\n\
-        {\"education_score\": 0.8, \"safety_score\": 0.9, \"age_appropriate\": true, \"overall_score\": 0.85, \"recommended_age\": \"3-6岁\", \"reasoning\": \"详细分析...\"}"
-    );
+// 单视频分析默认提示词
+const DEFAULT_SINGLE_ANALYSIS_PROMPT: &str = "请分析这个视频对{age_range}岁{age_group}的适宜性。请提供：\n\
+    1. 教育价值评分(0-1)\n\
+    2. 安全性评分(0-1)\n\
+    3. 是否适合儿童(true/false)\n\
+    4. 综合评分(0-1)\n\
+    5. 推荐年龄段\n\
+    6. 详细理由\n\n\
+    请以JSON格式返回：\n\
+    {\"education_score\": 0.8, \"safety_score\": 0.9, \"age_appropriate\": true, \"overall_score\": 0.85, \"recommended_age\": \"3-6岁\", \"reasoning\": \"详细分析...\"}";
+
+// 批量分析默认提示词
+const DEFAULT_BATCH_ANALYSIS_PROMPT: &str = r#"请分析以下视频对{age_range}岁{age_group}的适宜性。评判标准：
+1. 教育价值：是否有助于学习认知、语言、数学、科学等
+2. 内容安全：无暴力、恐怖、不当内容
+3. 年龄适宜：{age_criteria}
+4. 制作质量：画面清晰、音频清楚、制作精良
 
-    let content = format!("视频标题：{}\n视频描述：{}\n\n{}", title, description, prompt);
+总分计算：
+- 若时长不符合要求，直接将总分设为 min(原计算分数, 60)。
+- 若时长符合要求，综合各项指标取平均分作为总分。"#;
 
-    let request = OpenAIRequest {
-        model: "gpt-3.5-turbo".to_string(),
-        messages: vec![OpenAIMessage {
-            role: "user".to_string(),
-            content,
-        }],
-        max_tokens: 500,
-        temperature: 0.3,
-    };
+// 收到429时最多重试的次数
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+// 429响应没有 Retry-After 时的默认等待时间，以及允许等待的上限（秒）
+const DEFAULT_RETRY_AFTER_SECS: u64 = 5;
+const MAX_RETRY_AFTER_SECS: u64 = 60;
 
-    let response: OpenAIResponse = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| anyhow::anyhow!("OpenAI request failed: {}", e))?
-        .json()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to parse OpenAI response: {}", e))?;
-
-    let response_text = response
-        .choices
-        .first()
-        .ok_or_else(|| anyhow::anyhow!("No response from OpenAI"))?
-        .message
-        .content
-        .clone();
-
-    // 尝试解析JSON响应
-    if let Ok(json_response) = serde_json::from_str::<serde_json::Value>(&response_text) {
-        Ok(AIAnalysisResponse {
-            education_score: json_response["education_score"].as_f64().unwrap_or(0.7),
-            safety_score: json_response["safety_score"].as_f64().unwrap_or(0.8),
-            age_appropriate: json_response["age_appropriate"].as_bool().unwrap_or(true),
-            overall_score: json_response["overall_score"].as_f64().unwrap_or(0.75),
-            recommended_age: json_response["recommended_age"]
-                .as_str()
-                .unwrap_or("需要家长判断")
-                .to_string(),
-            reasoning: json_response["reasoning"]
-                .as_str()
-                .unwrap_or(&response_text)
-                .to_string(),
+// 按提供商的限流状态：收到429后，该提供商的所有并发请求都等到 Retry-After 之后再发送
+#[derive(Debug, Default)]
+struct RateLimiter {
+    resume_at: std::sync::Mutex<Option<tokio::time::Instant>>,
+}
+
+impl RateLimiter {
+    async fn wait(&self) {
+        let resume_at = *self.resume_at.lock().unwrap();
+        if let Some(resume_at) = resume_at {
+            if resume_at > tokio::time::Instant::now() {
+                tokio::time::sleep_until(resume_at).await;
+            }
+        }
+    }
+
+    fn pause_for(&self, delay: tokio::time::Duration) {
+        let until = tokio::time::Instant::now() + delay;
+        let mut resume_at = self.resume_at.lock().unwrap();
+        if resume_at.map_or(true, |current| current < until) {
+            *resume_at = Some(until);
+        }
+    }
+}
+
+// Retry-After 可以是秒数或HTTP日期
+fn retry_after_delay(response: &reqwest::Response) -> tokio::time::Duration {
+    let secs = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value.trim().parse::<u64>().ok().or_else(|| {
+                let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+                let delta = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+                Some(delta.num_seconds().max(0) as u64)
+            })
         })
-    } else {
-        // 如果JSON解析失败，返回默认值
-        Ok(AIAnalysisResponse {
-            education_score: 0.7,
-            safety_score: 0.8,
-            age_appropriate: true,
-            overall_score: 0.75,
-            recommended_age: "需要家长判断".to_string(),
-            reasoning: response_text,
+        .unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+
+    tokio::time::Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS))
+}
+
+// 发送请求，遇到429时按 Retry-After 暂停该提供商并重试
+async fn send_rate_limited(
+    limiter: &RateLimiter,
+    provider_name: &str,
+    build_request: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    let mut retries = 0;
+
+    loop {
+        limiter.wait().await;
+
+        let response = build_request()
+            .send()
+            .await
+            .map_err(|e| AppError::Network(format!("{} request failed: {}", provider_name, e)))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        if status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let body = response.text().await.unwrap_or_default();
+            return Err(provider_status_error(provider_name, status, &body).into());
+        }
+
+        let delay = retry_after_delay(&response);
+        limiter.pause_for(delay);
+
+        if retries >= MAX_RATE_LIMIT_RETRIES {
+            return Err(AppError::QuotaExceeded(format!(
+                "{} rate limit exceeded after {} retries",
+                provider_name,
+                retries
+            ))
+            .into());
+        }
+        retries += 1;
+        println!("⏳ {} rate limited, retrying in {}s ({}/{})", provider_name, delay.as_secs(), retries, MAX_RATE_LIMIT_RETRIES);
+    }
+}
+
+// 未在设置中指定或无法识别时使用的AI提供商
+const DEFAULT_AI_PROVIDER: &str = "openai";
+
+type ProviderFuture<'a, T> = futures::future::BoxFuture<'a, Result<T>>;
+
+// 单次调用所需的提供商配置
+#[derive(Debug, Clone, Default)]
+struct ProviderConfig {
+    api_key: String,
+    base_url: String,
+    model: String,
+    temperature: f32,
+    max_tokens: u32,
+    structured_output: bool,
+    max_concurrency: usize,
+}
+
+// 结构化输出描述：OpenAI 用作 json_schema，Anthropic 用作强制调用的工具
+#[derive(Debug)]
+struct StructuredOutput {
+    name: &'static str,
+    description: &'static str,
+    schema: serde_json::Value,
+}
+
+impl ProviderConfig {
+    fn concurrency(&self) -> usize {
+        self.max_concurrency.max(1)
+    }
+
+    // 按需申请的token数不超过设置中的上限
+    fn token_budget(&self, requested: u32) -> u32 {
+        if self.max_tokens == 0 {
+            requested
+        } else {
+            requested.min(self.max_tokens)
+        }
+    }
+}
+
+// 批量分析参数
+#[derive(Debug, Clone, Copy)]
+struct BatchAnalysisOptions<'a> {
+    custom_prompt: Option<&'a str>,
+    context: &'a PromptContext,
+    min_duration: Option<i32>,
+    max_duration: Option<i32>,
+}
+
+// AI提供商抽象
+// 每个后端只需实现 complete()，提示词构建和结果解析由默认方法共享
+trait AiProvider: Send + Sync {
+    // 提供商标识，与 AppSettings::ai_provider 对应
+    fn id(&self) -> &'static str;
+
+    // 从设置中读取该提供商的密钥、接口地址和模型
+    fn config(&self, settings: &AppSettings) -> ProviderConfig;
+
+    // 配置是否足以发起请求
+    fn is_configured(&self, config: &ProviderConfig) -> bool {
+        !config.api_key.is_empty()
+    }
+
+    // 发送单条用户消息，返回模型输出的文本
+    fn complete<'a>(
+        &'a self,
+        client: &'a Client,
+        config: &'a ProviderConfig,
+        content: String,
+        max_tokens: u32,
+    ) -> ProviderFuture<'a, String>;
+
+    // 要求模型按给定结构输出JSON文本
+    // 默认退化为普通请求，由解析端容错处理
+    fn complete_structured<'a>(
+        &'a self,
+        client: &'a Client,
+        config: &'a ProviderConfig,
+        content: String,
+        max_tokens: u32,
+        _output: &'a StructuredOutput,
+    ) -> ProviderFuture<'a, String> {
+        self.complete(client, config, content, max_tokens)
+    }
+
+    // 分析单个视频
+    fn analyze_video<'a>(
+        &'a self,
+        client: &'a Client,
+        config: &'a ProviderConfig,
+        title: &'a str,
+        description: &'a str,
+        custom_prompt: Option<&'a str>,
+        context: &'a PromptContext,
+    ) -> ProviderFuture<'a, AIAnalysisResponse> {
+        Box::pin(async move {
+            let prompt = context.render(custom_prompt.unwrap_or(DEFAULT_SINGLE_ANALYSIS_PROMPT));
+            let content = format!("视频标题：{}\n视频描述：{}\n\n{}", title, description, prompt);
+
+            let response_text = self.complete(client, config, content, config.token_budget(500)).await?;
+            parse_single_analysis_response(&response_text)
+        })
+    }
+
+    // 在一次请求中分析一组视频（调用方负责分块）
+    fn analyze_batch<'a>(
+        &'a self,
+        client: &'a Client,
+        config: &'a ProviderConfig,
+        videos: &'a [Video],
+        options: BatchAnalysisOptions<'a>,
+    ) -> ProviderFuture<'a, BatchAnalysisOutcome> {
+        Box::pin(async move {
+            let content = build_batch_analysis_prompt(videos, options);
+            let required_tokens = config.token_budget(calculate_required_tokens(videos.len()));
+
+            let response_text = if config.structured_output {
+                let output = batch_analysis_output();
+                self.complete_structured(client, config, content, required_tokens, &output).await?
+            } else {
+                self.complete(client, config, content, required_tokens).await?
+            };
+            parse_batch_analysis_response(videos, &response_text, options.min_duration, options.max_duration)
         })
     }
 }
 
-async fn analyze_with_anthropic(
-    client: &Client,
-    api_key: &str,
-    title: &str,
-    description: &str,
-    custom_prompt: Option<&str>,
-) -> Result<AIAnalysisResponse> {
-    let prompt = custom_prompt.unwrap_or(
-        "请分析这个视频对儿童的适宜性。请以JSON格式返回分析结果，包含education_score(0-1), safety_score(0-1), age_appropriate(布尔值), overall_score(0-1), recommended_age(字符串), reasoning(详细理由)。"
-    );
+#[derive(Default)]
+struct OpenAiProvider {
+    rate_limiter: RateLimiter,
+}
 
-    let content = format!("视频标题：{}\n视频描述：{}\n\n{}", title, description, prompt);
+impl OpenAiProvider {
+    async fn chat(
+        &self,
+        client: &Client,
+        config: &ProviderConfig,
+        content: String,
+        max_tokens: u32,
+        structured: Option<&StructuredOutput>,
+    ) -> Result<String> {
+        let response_format = structured.and_then(|output| openai_response_format(config, output));
+
+        let request = OpenAIRequest {
+            model: config.model.clone(),
+            messages: vec![OpenAIMessage {
+                role: "user".to_string(),
+                content,
+            }],
+            max_tokens,
+            temperature: config.temperature,
+            response_format,
+        };
 
-    let request = AnthropicRequest {
-        model: "claude-3-haiku-20240307".to_string(),
-        max_tokens: 500,
-        messages: vec![AnthropicMessage {
-            role: "user".to_string(),
-            content,
-        }],
-    };
+        let response: OpenAIResponse = send_rate_limited(&self.rate_limiter, "OpenAI", || {
+            openai_authorized(
+                client.post(openai_endpoint(&config.base_url, "chat/completions")),
+                &config.api_key,
+            )
+                .header("Content-Type", "application/json")
+                .json(&request)
+        })
+            .await?
+            .json()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to parse OpenAI response: {}", e))?;
 
-    let response: AnthropicResponse = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| anyhow::anyhow!("Anthropic request failed: {}", e))?
-        .json()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to parse Anthropic response: {}", e))?;
-
-    let response_text = response
-        .content
-        .first()
-        .ok_or_else(|| anyhow::anyhow!("No response from Anthropic"))?
-        .text
-        .clone();
-
-    // 尝试解析JSON响应
-    if let Ok(json_response) = serde_json::from_str::<serde_json::Value>(&response_text) {
-        Ok(AIAnalysisResponse {
-            education_score: json_response["education_score"].as_f64().unwrap_or(0.7),
-            safety_score: json_response["safety_score"].as_f64().unwrap_or(0.8),
-            age_appropriate: json_response["age_appropriate"].as_bool().unwrap_or(true),
-            overall_score: json_response["overall_score"].as_f64().unwrap_or(0.75),
-            recommended_age: json_response["recommended_age"]
-                .as_str()
-                .unwrap_or("需要家长判断")
-                .to_string(),
-            reasoning: json_response["reasoning"]
-                .as_str()
-                .unwrap_or(&response_text)
-                .to_string(),
+        let response_text = response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No response from OpenAI"))?
+            .message
+            .content;
+
+        Ok(response_text)
+    }
+}
+
+impl AiProvider for OpenAiProvider {
+    fn id(&self) -> &'static str {
+        "openai"
+    }
+
+    fn config(&self, settings: &AppSettings) -> ProviderConfig {
+        ProviderConfig {
+            api_key: settings.openai_api_key.clone().unwrap_or_default(),
+            base_url: settings.openai_base_url.clone(),
+            model: settings.openai_model.clone(),
+            temperature: settings.openai_temperature,
+            max_tokens: settings.openai_max_tokens,
+            structured_output: settings.use_structured_output,
+            max_concurrency: settings.ai_max_concurrency as usize,
+        }
+    }
+
+    // 自定义接口（本地模型）可以不需要密钥
+    fn is_configured(&self, config: &ProviderConfig) -> bool {
+        !config.api_key.is_empty() || !is_official_openai_url(&config.base_url)
+    }
+
+    fn complete<'a>(
+        &'a self,
+        client: &'a Client,
+        config: &'a ProviderConfig,
+        content: String,
+        max_tokens: u32,
+    ) -> ProviderFuture<'a, String> {
+        Box::pin(self.chat(client, config, content, max_tokens, None))
+    }
+
+    fn complete_structured<'a>(
+        &'a self,
+        client: &'a Client,
+        config: &'a ProviderConfig,
+        content: String,
+        max_tokens: u32,
+        output: &'a StructuredOutput,
+    ) -> ProviderFuture<'a, String> {
+        Box::pin(self.chat(client, config, content, max_tokens, Some(output)))
+    }
+}
+
+#[derive(Default)]
+struct AnthropicProvider {
+    rate_limiter: RateLimiter,
+}
+
+impl AnthropicProvider {
+    async fn message(
+        &self,
+        client: &Client,
+        config: &ProviderConfig,
+        content: String,
+        max_tokens: u32,
+        structured: Option<&StructuredOutput>,
+    ) -> Result<String> {
+        // 强制调用与输出结构同名的工具，工具参数即为结构化结果
+        let (tools, tool_choice) = match structured {
+            Some(output) => (
+                Some(vec![AnthropicTool {
+                    name: output.name.to_string(),
+                    description: output.description.to_string(),
+                    input_schema: output.schema.clone(),
+                }]),
+                Some(serde_json::json!({ "type": "tool", "name": output.name })),
+            ),
+            None => (None, None),
+        };
+
+        let request = AnthropicRequest {
+            model: config.model.clone(),
+            max_tokens,
+            temperature: config.temperature,
+            messages: vec![AnthropicMessage {
+                role: "user".to_string(),
+                content,
+            }],
+            tools,
+            tool_choice,
+        };
+
+        let response: AnthropicResponse = send_rate_limited(&self.rate_limiter, "Anthropic", || {
+            client
+                .post(format!("{}/messages", config.base_url))
+                .header("x-api-key", &config.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .header("Content-Type", "application/json")
+                .json(&request)
         })
+            .await?
+            .json()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to parse Anthropic response: {}", e))?;
+
+        if structured.is_some() {
+            if let Some(input) = response
+                .content
+                .iter()
+                .find(|block| block.content_type == "tool_use")
+                .and_then(|block| block.input.as_ref())
+            {
+                return Ok(input.to_string());
+            }
+        }
+
+        let response_text = response
+            .content
+            .into_iter()
+            .find_map(|block| block.text)
+            .ok_or_else(|| anyhow::anyhow!("No response from Anthropic"))?;
+
+        Ok(response_text)
+    }
+}
+
+impl AiProvider for AnthropicProvider {
+    fn id(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn config(&self, settings: &AppSettings) -> ProviderConfig {
+        ProviderConfig {
+            api_key: settings.anthropic_api_key.clone().unwrap_or_default(),
+            base_url: "https://api.anthropic.com/v1".to_string(),
+            model: settings.anthropic_model.clone(),
+            temperature: settings.anthropic_temperature,
+            max_tokens: settings.anthropic_max_tokens,
+            structured_output: settings.use_structured_output,
+            max_concurrency: settings.ai_max_concurrency as usize,
+        }
+    }
+
+    fn complete<'a>(
+        &'a self,
+        client: &'a Client,
+        config: &'a ProviderConfig,
+        content: String,
+        max_tokens: u32,
+    ) -> ProviderFuture<'a, String> {
+        Box::pin(self.message(client, config, content, max_tokens, None))
+    }
+
+    fn complete_structured<'a>(
+        &'a self,
+        client: &'a Client,
+        config: &'a ProviderConfig,
+        content: String,
+        max_tokens: u32,
+        output: &'a StructuredOutput,
+    ) -> ProviderFuture<'a, String> {
+        Box::pin(self.message(client, config, content, max_tokens, Some(output)))
+    }
+}
+
+// 拼接OpenAI兼容接口的完整地址，兼容末尾带或不带斜杠
+fn openai_endpoint(base_url: &str, path: &str) -> String {
+    let base_url = if base_url.trim().is_empty() {
+        OPENAI_DEFAULT_BASE_URL
     } else {
-        Ok(AIAnalysisResponse {
-            education_score: 0.7,
-            safety_score: 0.8,
-            age_appropriate: true,
-            overall_score: 0.75,
-            recommended_age: "需要家长判断".to_string(),
-            reasoning: response_text,
-        })
+        base_url.trim().trim_end_matches('/')
+    };
+    format!("{}/{}", base_url, path)
+}
+
+// 官方接口中支持 json_schema 结构化输出的模型前缀
+const OPENAI_JSON_SCHEMA_MODELS: &[&str] = &["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"];
+// 只支持 json_object 模式的旧模型前缀
+const OPENAI_JSON_OBJECT_MODELS: &[&str] = &["gpt-3.5-turbo", "gpt-4-turbo", "gpt-4-1106", "gpt-4-0125"];
+
+// 按模型选择 response_format：json_schema 保证结构，json_object 只保证是JSON，其余模型发送普通请求
+// 自定义接口（Ollama / vLLM 等）由用户自行开启，沿用 json_schema
+fn openai_response_format(config: &ProviderConfig, output: &StructuredOutput) -> Option<serde_json::Value> {
+    let model = config.model.trim().to_ascii_lowercase();
+    let supports_schema = !is_official_openai_url(&config.base_url)
+        || OPENAI_JSON_SCHEMA_MODELS.iter().any(|prefix| model.starts_with(prefix));
+
+    if supports_schema {
+        Some(serde_json::json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": output.name,
+                "description": output.description,
+                "strict": true,
+                "schema": output.schema,
+            }
+        }))
+    } else if OPENAI_JSON_OBJECT_MODELS.iter().any(|prefix| model.starts_with(prefix)) {
+        // json_object 要求提示词中出现 "JSON"，批量提示词已包含
+        Some(serde_json::json!({ "type": "json_object" }))
+    } else {
+        None
+    }
+}
+
+fn is_official_openai_url(base_url: &str) -> bool {
+    let base_url = base_url.trim();
+    base_url.is_empty() || base_url.trim_end_matches('/') == OPENAI_DEFAULT_BASE_URL
+}
+
+// 本地服务通常不需要密钥，未配置时不发送 Authorization 头
+fn openai_authorized(builder: reqwest::RequestBuilder, api_key: &str) -> reqwest::RequestBuilder {
+    if api_key.is_empty() {
+        builder
+    } else {
+        builder.header("Authorization", format!("Bearer {}", api_key))
     }
 }
 
+// AI提供商注册表，按 AppSettings::ai_provider 查找
+struct ProviderRegistry {
+    providers: HashMap<&'static str, Arc<dyn AiProvider>>,
+}
+
+impl ProviderRegistry {
+    fn new() -> Self {
+        let mut registry = Self {
+            providers: HashMap::new(),
+        };
+        registry.register(Arc::new(OpenAiProvider::default()));
+        registry.register(Arc::new(AnthropicProvider::default()));
+        registry
+    }
+
+    fn register(&mut self, provider: Arc<dyn AiProvider>) {
+        self.providers.insert(provider.id(), provider);
+    }
+
+    // 未知的提供商回退到默认提供商（与旧版 match 的 `_` 分支一致）
+    fn resolve(&self, id: &str) -> Arc<dyn AiProvider> {
+        if let Some(provider) = self.providers.get(id) {
+            return provider.clone();
+        }
+
+        println!("⚠️ Unknown AI provider '{}', falling back to {}", id, DEFAULT_AI_PROVIDER);
+        self.providers
+            .get(DEFAULT_AI_PROVIDER)
+            .cloned()
+            .expect("default AI provider must be registered")
+    }
+}
+
+// 解析单视频分析结果
+fn parse_single_analysis_response(response_text: &str) -> Result<AIAnalysisResponse> {
+    let json_response = extract_json_payload(response_text)
+        .ok_or_else(|| anyhow::anyhow!("AI response does not contain valid JSON"))?;
+
+    // 评分字段缺失或超出 0-1 范围时视为未验证，不使用默认的“通过”值
+    let required_f64 = |field: &str| {
+        let value = json_response[field]
+            .as_f64()
+            .ok_or_else(|| anyhow::anyhow!("AI response is missing '{}'", field))?;
+        if !(0.0..=1.0).contains(&value) {
+            return Err(anyhow::anyhow!("AI response '{}' {} is out of range 0..=1", field, value));
+        }
+        Ok(value)
+    };
+
+    Ok(AIAnalysisResponse {
+        education_score: required_f64("education_score")?,
+        safety_score: required_f64("safety_score")?,
+        age_appropriate: json_response["age_appropriate"]
+            .as_bool()
+            .ok_or_else(|| anyhow::anyhow!("AI response is missing 'age_appropriate'"))?,
+        overall_score: required_f64("overall_score")?,
+        recommended_age: json_response["recommended_age"]
+            .as_str()
+            .unwrap_or("需要家长判断")
+            .to_string(),
+        reasoning: json_response["reasoning"]
+            .as_str()
+            .unwrap_or(response_text)
+            .to_string(),
+    })
+}
+
 // Format duration in seconds to human readable format (e.g., "4分13秒")
 fn format_duration(seconds: i32) -> String {
     let hours = seconds / 3600;
     let minutes = (seconds % 3600) / 60;
     let secs = seconds % 60;
-    
+
     if hours > 0 {
         format!("{}小时{}分{}秒", hours, minutes, secs)
     } else if minutes > 0 {
@@ -1201,115 +3452,16 @@ fn calculate_required_tokens(video_count: usize) -> u32 {
     (base_tokens + (video_count as u32 * per_video_tokens)).min(8000)
 }
 
-// Process videos in chunks to avoid token limits
-async fn process_videos_in_chunks(
-    client: &Client,
-    api_key: &str,
-    videos: &[Video],
-    chunk_size: usize,
-    custom_prompt: Option<&str>,
-    min_duration: Option<i32>,
-    max_duration: Option<i32>,
-    provider: &str,
-) -> Result<Vec<Video>> {
-    let mut all_results = Vec::new();
-    
-    for chunk in videos.chunks(chunk_size) {
-        let chunk_result = match provider {
-            "anthropic" => {
-                analyze_batch_with_anthropic_impl(
-                    client,
-                    api_key,
-                    chunk,
-                    custom_prompt,
-                    min_duration,
-                    max_duration,
-                ).await
-            }
-            _ => {
-                analyze_batch_with_openai_impl(
-                    client,
-                    api_key,
-                    chunk,
-                    custom_prompt,
-                    min_duration,
-                    max_duration,
-                ).await
-            }
-        };
-        
-        match chunk_result {
-            Ok(mut chunk_results) => {
-                all_results.append(&mut chunk_results);
-            }
-            Err(e) => {
-                println!("⚠️ Chunk processing failed: {}", e);
-                // Continue processing other chunks even if one fails
-            }
-        }
-    }
-    
-    Ok(all_results)
-}
-
-// Batch analyze videos with OpenAI
-async fn analyze_batch_with_openai(
-    client: &Client,
-    api_key: &str,
-    videos: &[Video],
-    custom_prompt: Option<&str>,
-    min_duration: Option<i32>,
-    max_duration: Option<i32>,
-) -> Result<Vec<Video>> {
-    if videos.is_empty() {
-        return Ok(vec![]);
-    }
-
-    // Split videos into chunks if necessary
-    if videos.len() > MAX_VIDEOS_PER_BATCH {
-        println!("📦 Splitting {} videos into chunks of {}", videos.len(), MAX_VIDEOS_PER_BATCH);
-        
-        return process_videos_in_chunks(
-            client,
-            api_key,
-            videos,
-            MAX_VIDEOS_PER_BATCH,
-            custom_prompt,
-            min_duration,
-            max_duration,
-            "openai",
-        ).await;
-    }
-
-    // Process directly if within limit
-    analyze_batch_with_openai_impl(client, api_key, videos, custom_prompt, min_duration, max_duration).await
-}
-
-// Implementation of OpenAI batch analysis
-async fn analyze_batch_with_openai_impl(
-    client: &Client,
-    api_key: &str,
-    videos: &[Video],
-    custom_prompt: Option<&str>,
-    min_duration: Option<i32>,
-    max_duration: Option<i32>,
-) -> Result<Vec<Video>> {
-    let default_prompt = r#"请分析以下儿童视频的适宜性。评判标准：
-1. 教育价值：是否有助于学习认知、语言、数学、科学等
-2. 内容安全：无暴力、恐怖、不当内容
-3. 年龄适宜：符合学前儿童认知水平
-4. 制作质量：画面清晰、音频清楚、制作精良
-
-总分计算：
-- 若时长不符合要求，直接将总分设为 min(原计算分数, 60)。
-- 若时长符合要求，综合各项指标取平均分作为总分。"#;
+// Build the batch analysis prompt shared by all providers
+fn build_batch_analysis_prompt(videos: &[Video], options: BatchAnalysisOptions<'_>) -> String {
+    let user_prompt = options
+        .context
+        .render(options.custom_prompt.unwrap_or(DEFAULT_BATCH_ANALYSIS_PROMPT));
 
-    let user_prompt = custom_prompt.unwrap_or(default_prompt);
-    
     // Build video list for analysis
     let video_list: String = videos.iter().enumerate().map(|(index, video)| {
         format!(
-            "视频{}:\n标题：{}\n时长：{} ({}秒)\n描述：{}\n喜欢人数：{}\n观看人数：{}\n发布时间：{}\n频道：{}\n字幕：{}",
+            "视频{}:\n标题：{}\n时长：{} ({}秒)\n描述：{}\n喜欢人数：{}\n观看人数：{}\n发布时间：{}\n频道：{}\n主题：{}\n字幕：{}",
             index + 1,
             video.title,
             video.duration.map(format_duration).unwrap_or_else(|| "未知".to_string()),
@@ -1319,164 +3471,402 @@ async fn analyze_batch_with_openai_impl(
             video.view_count.unwrap_or(0),
             video.published_at.as_deref().unwrap_or("无发布时间"),
             video.channel_title.as_deref().unwrap_or("未知"),
+            video.topic_categories
+                .as_ref()
+                .filter(|topics| !topics.is_empty())
+                .map(|topics| topics.join("、"))
+                .unwrap_or_else(|| "未知".to_string()),
             video.subtitles.as_deref().unwrap_or("无字幕信息")
         )
     }).collect::<Vec<String>>().join("\n\n");
 
-    let content = format!(
-        "{}\n\n输出要求：\n- 返回 JSON 格式\n- 只返回同时满足：score >= 70 且 时长符合要求 的视频\n- 字段：\n{{\n  \"videos\": [\n    {{\n      \"index\": 1,\n      \"score\": 0-100,\n      \"suitable\": true/false,   // true 表示总分≥70且时长合格\n      \"reason\": \"评分理由\",\n      \"educational_value\": 0-100,\n      \"safety_score\": 0-100\n    }}\n  ]\n}}\n\n请分析以下{}个视频：\n\n{}",
+    format!(
+        "{}\n\n输出要求：\n- 返回 JSON 格式\n- 返回全部视频的评分，只有 score >= 70 且 时长符合要求 时 suitable 才为 true\n- 字段：\n{{\n  \"videos\": [\n    {{\n      \"index\": 1,\n      \"score\": 0-100,\n      \"suitable\": true/false,   // true 表示总分≥70且时长合格\n      \"reason\": \"评分理由\",\n      \"educational_value\": 0-100,\n      \"safety_score\": 0-100,\n      \"age_fit\": 0-100,\n      \"production_quality\": 0-100,\n      \"recommended_age\": \"推荐年龄段\"\n    }}\n  ]\n}}\n\n请分析以下{}个视频：\n\n{}",
         user_prompt,
         videos.len(),
         video_list
-    );
+    )
+}
 
-    let required_tokens = calculate_required_tokens(videos.len());
-    
-    let request = OpenAIRequest {
-        model: "gpt-3.5-turbo".to_string(),
-        messages: vec![OpenAIMessage {
-            role: "user".to_string(),
-            content,
-        }],
-        max_tokens: required_tokens,
-        temperature: 0.3,
-    };
+// Process videos in chunks to avoid token limits
+// 各块并发请求（不超过设置的并发上限），结果按块的原始顺序合并
+async fn process_videos_in_chunks(
+    provider: &dyn AiProvider,
+    client: &Client,
+    config: &ProviderConfig,
+    videos: &[Video],
+    chunk_size: usize,
+    options: BatchAnalysisOptions<'_>,
+) -> Result<BatchAnalysisOutcome> {
+    let mut all_results = BatchAnalysisOutcome::default();
 
-    let response: OpenAIResponse = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| anyhow::anyhow!("OpenAI batch request failed: {}", e))?
-        .json()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to parse OpenAI batch response: {}", e))?;
+    let chunk_futures: Vec<_> = videos
+        .chunks(chunk_size)
+        .map(|chunk| provider.analyze_batch(client, config, chunk, options))
+        .collect();
+    let chunk_results: Vec<_> = futures::stream::iter(chunk_futures)
+        .buffered(config.concurrency())
+        .collect()
+        .await;
 
-    let response_text = response
-        .choices
-        .first()
-        .ok_or_else(|| anyhow::anyhow!("No response from OpenAI"))?
-        .message
-        .content
-        .clone();
+    for (chunk_index, (chunk, result)) in videos.chunks(chunk_size).zip(chunk_results).enumerate() {
+        match result {
+            Ok(mut chunk_results) => {
+                all_results.videos.append(&mut chunk_results.videos);
+                all_results.records.append(&mut chunk_results.records);
+                // 将块内序号换算为整个列表中的序号
+                let offset = chunk_index * chunk_size;
+                all_results.invalid_items.extend(chunk_results.invalid_items.into_iter().map(|mut item| {
+                    item.index = item.index.map(|index| index + offset);
+                    item
+                }));
+            }
+            Err(e) => {
+                println!("⚠️ Chunk {} failed, marking {} videos as unverified: {}", chunk_index, chunk.len(), e);
+                // Continue processing other chunks even if one fails
+                all_results.videos.extend(chunk.iter().cloned().map(|mut video| {
+                    video.mark_unverified();
+                    video
+                }));
+                all_results.failed_chunks.push(FailedChunk {
+                    chunk_index,
+                    video_ids: chunk.iter().map(|video| video.id.clone()).collect(),
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
 
-    // Parse batch analysis response
-    parse_batch_analysis_response(videos, &response_text, min_duration, max_duration)
+    Ok(all_results)
 }
 
-// Batch analyze videos with Anthropic
-async fn analyze_batch_with_anthropic(
+// Batch analyze videos with the given provider, splitting into chunks if necessary
+async fn analyze_batch_with_provider(
+    provider: &dyn AiProvider,
     client: &Client,
-    api_key: &str,
+    config: &ProviderConfig,
     videos: &[Video],
-    custom_prompt: Option<&str>,
-    min_duration: Option<i32>,
-    max_duration: Option<i32>,
-) -> Result<Vec<Video>> {
+    options: BatchAnalysisOptions<'_>,
+) -> Result<BatchAnalysisOutcome> {
     if videos.is_empty() {
-        return Ok(vec![]);
+        return Ok(BatchAnalysisOutcome::default());
     }
 
     // Split videos into chunks if necessary
     if videos.len() > MAX_VIDEOS_PER_BATCH {
         println!("📦 Splitting {} videos into chunks of {}", videos.len(), MAX_VIDEOS_PER_BATCH);
-        
+
         return process_videos_in_chunks(
+            provider,
             client,
-            api_key,
+            config,
             videos,
             MAX_VIDEOS_PER_BATCH,
-            custom_prompt,
-            min_duration,
-            max_duration,
-            "anthropic",
+            options,
         ).await;
     }
 
     // Process directly if within limit
-    analyze_batch_with_anthropic_impl(client, api_key, videos, custom_prompt, min_duration, max_duration).await
+    provider.analyze_batch(client, config, videos, options).await
 }
 
-// Implementation of Anthropic batch analysis
-async fn analyze_batch_with_anthropic_impl(
-    client: &Client,
-    api_key: &str,
-    videos: &[Video],
-    custom_prompt: Option<&str>,
-    min_duration: Option<i32>,
-    max_duration: Option<i32>,
-) -> Result<Vec<Video>> {
-    let default_prompt = r#"请分析以下儿童视频的适宜性。评判标准：
-1. 教育价值：是否有助于学习认知、语言、数学、科学等
-2. 内容安全：无暴力、恐怖、不当内容
-3. 年龄适宜：符合学前儿童认知水平
-4. 制作质量：画面清晰、音频清楚、制作精良
+// AI分析缓存的查询条件：同一视频、提供商、模型和提示词
+struct AnalysisCacheKey<'a> {
+    provider: &'a str,
+    model: &'a str,
+    prompt_hash: String,
+    max_age_days: i32,
+}
 
-总分计算：
-- 若时长不符合要求，直接将总分设为 min(原计算分数, 60)。
-- 若时长符合要求，综合各项指标取平均分作为总分。"#;
+impl<'a> AnalysisCacheKey<'a> {
+    fn new(provider: &'a str, config: &'a ProviderConfig, options: BatchAnalysisOptions<'_>, max_age_days: i32) -> Self {
+        Self {
+            provider,
+            model: &config.model,
+            prompt_hash: batch_prompt_hash(options),
+            max_age_days,
+        }
+    }
+}
 
-    let user_prompt = custom_prompt.unwrap_or(default_prompt);
-    
-    // Build video list for analysis
-    let video_list: String = videos.iter().enumerate().map(|(index, video)| {
-        format!(
-            "视频{}:\n标题：{}\n时长：{} ({}秒)\n描述：{}\n喜欢人数：{}\n观看人数：{}\n发布时间：{}\n频道：{}\n字幕：{}",
-            index + 1,
-            video.title,
-            video.duration.map(format_duration).unwrap_or_else(|| "未知".to_string()),
-            video.duration.unwrap_or(0),
-            video.description.as_deref().unwrap_or("无描述"),
-            video.like_count.unwrap_or(0),
-            video.view_count.unwrap_or(0),
-            video.published_at.as_deref().unwrap_or("无发布时间"),
-            video.channel_title.as_deref().unwrap_or("未知"),
-            video.subtitles.as_deref().unwrap_or("无字幕信息")
-        )
-    }).collect::<Vec<String>>().join("\n\n");
+// 渲染后的提示词及时长要求决定评分结果，共同参与哈希
+fn batch_prompt_hash(options: BatchAnalysisOptions<'_>) -> String {
+    let prompt = build_batch_analysis_prompt(&[], options);
+    let fingerprint = format!("{}|{:?}|{:?}", prompt, options.min_duration, options.max_duration);
+    format!("{:016x}", fnv1a_64(fingerprint.as_bytes()))
+}
 
-    let content = format!(
-        "{}\n\n输出要求：\n- 返回 JSON 格式\n- 只返回同时满足：score >= 70 且 时长符合要求 的视频\n- 字段：\n{{\n  \"videos\": [\n    {{\n      \"index\": 1,\n      \"score\": 0-100,\n      \"suitable\": true/false,   // true 表示总分≥70且时长合格\n      \"reason\": \"评分理由\",\n      \"educational_value\": 0-100,\n      \"safety_score\": 0-100\n    }}\n  ]\n}}\n\n请分析以下{}个视频：\n\n{}",
-        user_prompt,
-        videos.len(),
-        video_list
+// FNV-1a：结果跨版本稳定，适合持久化的缓存键
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+async fn load_cached_analyses(
+    db: &SqlitePool,
+    key: &AnalysisCacheKey<'_>,
+    videos: &[Video],
+) -> Result<HashMap<String, VideoAnalysisRecord>> {
+    if videos.is_empty() || key.max_age_days <= 0 {
+        return Ok(HashMap::new());
+    }
+
+    let cutoff = (chrono::Utc::now() - chrono::Duration::days(i64::from(key.max_age_days))).to_rfc3339();
+    let placeholders = vec!["?"; videos.len()].join(", ");
+    let sql = format!(
+        "SELECT video_id, ai_score, education_score, safety_score, approved, reasoning,
+                recommended_age, age_fit_score, quality_score
+         FROM ai_analyses
+         WHERE provider = ? AND model = ? AND prompt_hash = ? AND analyzed_at >= ? AND video_id IN ({})",
+        placeholders
     );
 
-    let required_tokens = calculate_required_tokens(videos.len());
-    
-    let request = AnthropicRequest {
-        model: "claude-3-haiku-20240307".to_string(),
-        max_tokens: required_tokens,
-        messages: vec![AnthropicMessage {
-            role: "user".to_string(),
-            content,
-        }],
-    };
+    type Row = (String, f64, f64, f64, bool, Option<String>, Option<String>, Option<f64>, Option<f64>);
+    let mut query = sqlx::query_as::<_, Row>(&sql)
+        .bind(key.provider)
+        .bind(key.model)
+        .bind(&key.prompt_hash)
+        .bind(&cutoff);
+    for video in videos {
+        query = query.bind(&video.id);
+    }
 
-    let response: AnthropicResponse = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| anyhow::anyhow!("Anthropic batch request failed: {}", e))?
-        .json()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to parse Anthropic batch response: {}", e))?;
+    let rows = query.fetch_all(db).await?;
+    Ok(rows
+        .into_iter()
+        .map(|(video_id, ai_score, education_score, safety_score, approved, reasoning, recommended_age, age_fit_score, quality_score)| {
+            let record = VideoAnalysisRecord {
+                video_id: video_id.clone(),
+                ai_score,
+                education_score,
+                safety_score,
+                approved,
+                reasoning: reasoning.unwrap_or_default(),
+                recommended_age,
+                age_fit_score,
+                quality_score,
+            };
+            (video_id, record)
+        })
+        .collect())
+}
+
+async fn store_cached_analyses(
+    db: &SqlitePool,
+    key: &AnalysisCacheKey<'_>,
+    records: &[VideoAnalysisRecord],
+) -> Result<()> {
+    if records.is_empty() || key.max_age_days <= 0 {
+        return Ok(());
+    }
+
+    let analyzed_at = chrono::Utc::now().to_rfc3339();
+    let mut tx = db.begin().await?;
+
+    for record in records {
+        sqlx::query(
+            "INSERT OR REPLACE INTO ai_analyses
+             (video_id, provider, model, prompt_hash, ai_score, education_score, safety_score, approved, reasoning,
+              recommended_age, age_fit_score, quality_score, analyzed_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&record.video_id)
+        .bind(key.provider)
+        .bind(key.model)
+        .bind(&key.prompt_hash)
+        .bind(record.ai_score)
+        .bind(record.education_score)
+        .bind(record.safety_score)
+        .bind(record.approved)
+        .bind(&record.reasoning)
+        .bind(&record.recommended_age)
+        .bind(record.age_fit_score)
+        .bind(record.quality_score)
+        .bind(&analyzed_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
 
-    let response_text = response
-        .content
-        .first()
-        .ok_or_else(|| anyhow::anyhow!("No response from Anthropic"))?
-        .text
-        .clone();
+// 先查AI分析缓存，只把没有有效缓存的视频发送给模型
+async fn analyze_batch_cached(
+    db: &SqlitePool,
+    provider: &dyn AiProvider,
+    client: &Client,
+    config: &ProviderConfig,
+    videos: &[Video],
+    options: BatchAnalysisOptions<'_>,
+    cache_days: i32,
+) -> Result<BatchAnalysisOutcome> {
+    let key = AnalysisCacheKey::new(provider.id(), config, options, cache_days);
+
+    // 缓存读取失败时退化为全部重新分析
+    let cached = load_cached_analyses(db, &key, videos).await.unwrap_or_else(|e| {
+        println!("⚠️ Failed to load cached AI analyses: {}", e);
+        HashMap::new()
+    });
+
+    let uncached: Vec<Video> = videos
+        .iter()
+        .filter(|video| !cached.contains_key(&video.id))
+        .cloned()
+        .collect();
+
+    if !cached.is_empty() {
+        println!("💾 AI analysis cache hit for {}/{} videos", cached.len(), videos.len());
+    }
+
+    let mut outcome = analyze_batch_with_provider(provider, client, config, &uncached, options).await?;
+
+    if let Err(e) = store_cached_analyses(db, &key, &outcome.records).await {
+        println!("⚠️ Failed to store AI analyses: {}", e);
+    }
+
+    // 按原始搜索顺序合并缓存命中和新分析的结果
+    let mut fresh_videos: HashMap<String, Video> = outcome
+        .videos
+        .drain(..)
+        .map(|video| (video.id.clone(), video))
+        .collect();
+    let mut fresh_records: HashMap<String, VideoAnalysisRecord> = outcome
+        .records
+        .drain(..)
+        .map(|record| (record.video_id.clone(), record))
+        .collect();
+
+    for video in videos {
+        if let Some(record) = cached.get(&video.id) {
+            if record.approved {
+                let mut video = video.clone();
+                record.apply_to(&mut video);
+                outcome.videos.push(video);
+            }
+            outcome.records.push(record.clone());
+        } else {
+            if let Some(fresh) = fresh_videos.remove(&video.id) {
+                outcome.videos.push(fresh);
+            }
+            if let Some(record) = fresh_records.remove(&video.id) {
+                outcome.records.push(record);
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+// 从模型输出中提取JSON
+// 兼容 ```json 代码块、前后附带的说明文字，以及因token上限被截断的JSON
+fn extract_json_payload(response_text: &str) -> Option<serde_json::Value> {
+    let trimmed = response_text.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Some(value);
+    }
+
+    // 说明文字中也可能出现括号（如 "results [below]: {...}"），失败时从下一个括号重试
+    let unfenced = strip_code_fence(trimmed).unwrap_or(trimmed);
+    unfenced
+        .match_indices(['{', '['])
+        .filter_map(|(start, _)| scan_json_value(&unfenced[start..]))
+        .find_map(|candidate| serde_json::from_str(&candidate).ok())
+}
+
+// 取出第一个 ``` 代码块中的内容
+fn strip_code_fence(text: &str) -> Option<&str> {
+    let fence_start = text.find("```")?;
+    let after_fence = &text[fence_start + 3..];
+    // 跳过语言标记（如 json）所在的行
+    let body_start = after_fence.find('\n').map(|i| i + 1).unwrap_or(0);
+    let body = &after_fence[body_start..];
+    let body_end = body.find("```").unwrap_or(body.len());
+    Some(body[..body_end].trim())
+}
+
+// 从 { 或 [ 开始扫描出第一个完整的JSON值
+// 如果文本在中途被截断，则退回到最后一个完整的对象/数组并补齐括号
+fn scan_json_value(text: &str) -> Option<String> {
+    let mut stack: Vec<char> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut last_complete: Option<(usize, Vec<char>)> = None;
+
+    for (i, c) in text.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => stack.push(c),
+            '}' | ']' => {
+                let open = stack.pop()?;
+                if (open == '{') != (c == '}') {
+                    return None;
+                }
+                if stack.is_empty() {
+                    return Some(text[..=i].to_string());
+                }
+                last_complete = Some((i + 1, stack.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    let (end, open_brackets) = last_complete?;
+    let mut repaired = text[..end].to_string();
+    for open in open_brackets.iter().rev() {
+        repaired.push(if *open == '{' { '}' } else { ']' });
+    }
+    Some(repaired)
+}
+
+// 批量分析的解析结果
+// videos 为通过筛选或未验证的视频，records 为所有有效评分（包括未通过的），用于写入分析缓存
+#[derive(Debug, Default)]
+struct BatchAnalysisOutcome {
+    videos: Vec<Video>,
+    invalid_items: Vec<InvalidBatchItem>,
+    failed_chunks: Vec<FailedChunk>,
+    records: Vec<VideoAnalysisRecord>,
+}
+
+// 单个视频的一条有效AI评分
+#[derive(Debug, Clone)]
+struct VideoAnalysisRecord {
+    video_id: String,
+    ai_score: f64,
+    education_score: f64,
+    safety_score: f64,
+    approved: bool,
+    reasoning: String,
+    recommended_age: Option<String>,
+    age_fit_score: Option<f64>,
+    quality_score: Option<f64>,
+}
 
-    // Parse batch analysis response
-    parse_batch_analysis_response(videos, &response_text, min_duration, max_duration)
+impl VideoAnalysisRecord {
+    fn apply_to(&self, video: &mut Video) {
+        video.ai_score = Some(self.ai_score);
+        video.education_score = Some(self.education_score);
+        video.safety_score = Some(self.safety_score);
+        video.age_appropriate = Some(self.approved);
+        video.ai_reasoning = Some(self.reasoning.clone()).filter(|reasoning| !reasoning.is_empty());
+        video.recommended_age = self.recommended_age.clone();
+        video.age_fit_score = self.age_fit_score;
+        video.quality_score = self.quality_score;
+        video.analysis_status = Some(ANALYSIS_VERIFIED.to_string());
+    }
 }
 
 // Parse batch analysis response and apply scores to videos
@@ -1485,51 +3875,109 @@ fn parse_batch_analysis_response(
     response_text: &str,
     min_duration: Option<i32>,
     max_duration: Option<i32>,
-) -> Result<Vec<Video>> {
+) -> Result<BatchAnalysisOutcome> {
     let min_duration_secs = min_duration.unwrap_or(2) * 60;
     let max_duration_secs = max_duration.unwrap_or(30) * 60;
-    
-    // Try to parse JSON response
-    if let Ok(json_response) = serde_json::from_str::<serde_json::Value>(response_text) {
-        if let Some(analyzed_videos) = json_response["videos"].as_array() {
-            let mut result_videos = Vec::new();
-            
-            for analyzed in analyzed_videos {
-                if let Some(index) = analyzed["index"].as_u64() {
-                    let idx = (index as usize) - 1; // Convert to 0-based index
-                    
-                    if idx < videos.len() {
-                        let mut video = videos[idx].clone();
-                        
-                        // Check duration requirements
-                        let duration_ok = video.duration.map_or(false, |d| {
-                            d >= min_duration_secs && d <= max_duration_secs
-                        });
-                        
-                        let score = analyzed["score"].as_f64().unwrap_or(0.0) / 100.0;
-                        let educational_value = analyzed["educational_value"].as_f64().unwrap_or(70.0) / 100.0;
-                        let safety_score = analyzed["safety_score"].as_f64().unwrap_or(80.0) / 100.0;
-                        let suitable = analyzed["suitable"].as_bool().unwrap_or(false) && duration_ok;
-                        
-                        if suitable && score >= 0.7 {
-                            video.ai_score = Some(score);
-                            video.education_score = Some(educational_value);
-                            video.safety_score = Some(safety_score);
-                            video.age_appropriate = Some(suitable);
-                            
-                            result_videos.push(video);
-                        }
+
+    // 解析失败不能视为通过，交由调用方回退或标记为未验证
+    let payload = extract_json_payload(response_text).ok_or_else(|| {
+        println!("⚠️ Batch analysis parsing failed");
+        anyhow::anyhow!("Batch analysis response could not be parsed")
+    })?;
+
+    // 同时接受 {"videos": [...]} 和直接返回的数组
+    let items = match payload {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(mut object) => match object.remove("videos") {
+            Some(serde_json::Value::Array(items)) => items,
+            _ => return Err(anyhow::anyhow!("Batch analysis response has no 'videos' array")),
+        },
+        _ => return Err(anyhow::anyhow!("Batch analysis response is not a JSON object")),
+    };
+
+    let mut outcome = BatchAnalysisOutcome::default();
+    let mut seen_indexes = std::collections::HashSet::new();
+
+    for item in items {
+        let raw_index = item
+            .get("index")
+            .and_then(|index| index.as_u64().or_else(|| index.as_str()?.trim().parse().ok()))
+            .map(|index| index as usize)
+            .filter(|index| (1..=videos.len()).contains(index));
+
+        let analysis = serde_json::from_value::<BatchVideoAnalysis>(item)
+            .map_err(|e| e.to_string())
+            .and_then(|analysis| analysis.validate(videos.len()).map(|_| analysis))
+            .and_then(|analysis| {
+                if seen_indexes.insert(analysis.index) {
+                    Ok(analysis)
+                } else {
+                    Err(format!("duplicate result for index {}", analysis.index))
+                }
+            });
+
+        let analysis = match analysis {
+            Ok(analysis) => analysis,
+            Err(error) => {
+                println!("⚠️ Invalid batch analysis item (index {:?}): {}", raw_index, error);
+                // 序号可识别时保留该视频并标记为未验证，由未验证视频策略决定去留
+                if let Some(index) = raw_index {
+                    if seen_indexes.insert(index) {
+                        let mut video = videos[index - 1].clone();
+                        video.mark_unverified();
+                        outcome.videos.push(video);
                     }
                 }
+                outcome.invalid_items.push(InvalidBatchItem {
+                    index: raw_index,
+                    video_id: raw_index.map(|index| videos[index - 1].id.clone()),
+                    error,
+                });
+                continue;
             }
-            
-            return Ok(result_videos);
+        };
+
+        let mut video = videos[analysis.index - 1].clone();
+
+        // Check duration requirements
+        let duration_ok = video.duration.is_some_and(|d| {
+            d >= min_duration_secs && d <= max_duration_secs
+        });
+
+        let score = analysis.score / 100.0;
+        let record = VideoAnalysisRecord {
+            video_id: video.id.clone(),
+            ai_score: score,
+            education_score: analysis.educational_value / 100.0,
+            safety_score: analysis.safety_score / 100.0,
+            approved: analysis.suitable && duration_ok && score >= 0.7,
+            reasoning: analysis.reason,
+            recommended_age: analysis.recommended_age,
+            age_fit_score: analysis.age_fit.map(|value| value / 100.0),
+            quality_score: analysis.production_quality.map(|value| value / 100.0),
+        };
+
+        if record.approved {
+            record.apply_to(&mut video);
+            outcome.videos.push(video);
         }
+        outcome.records.push(record);
     }
-    
-    // Fallback: analyze individually if batch parsing fails
-    println!("⚠️ Batch analysis parsing failed, falling back to individual analysis");
-    Ok(videos.to_vec())
+
+    // 模型遗漏的视频没有评分，标记为未验证
+    for (position, video) in videos.iter().enumerate() {
+        if !seen_indexes.contains(&(position + 1)) {
+            let mut video = video.clone();
+            video.mark_unverified();
+            outcome.videos.push(video);
+        }
+    }
+
+    if !outcome.invalid_items.is_empty() {
+        println!("⚠️ {} batch analysis items were invalid", outcome.invalid_items.len());
+    }
+
+    Ok(outcome)
 }
 
 // Tauri命令实现
@@ -1537,104 +3985,268 @@ fn parse_batch_analysis_response(
 async fn search_videos(
     request: SearchRequest,
     state: tauri::State<'_, AppState>,
-) -> Result<SearchResponse, String> {
+) -> Result<SearchResponse, AppError> {
     let start_time = std::time::Instant::now();
     println!("🔍 Production: Searching for '{}' on {}", request.query, request.platform);
 
-    let settings = state.settings.lock().await;
+    let platform = Platform::from_id(&request.platform)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unsupported platform '{}'", request.platform)))?;
+
+    // 合并当前儿童档案的年龄段和筛选条件；档案指定了筛选模式时以档案为准
+    let (profile, settings) = resolve_viewing_profile(&state, request.profile_id, request.parent_token.as_deref()).await?;
+    let filter_mode = profile.filter_mode.clone().unwrap_or_else(|| request.filter_mode.clone());
+    println!("👶 Using profile '{}' ({} years)", profile.name, settings.child_age);
+    ensure_screen_time_available(&state, &profile, &settings).await?;
+    ensure_within_schedule(&state, &profile).await?;
     
     // 检查是否配置了YouTube API密钥
     let youtube_api_key = match &settings.youtube_api_key {
         Some(key) if !key.is_empty() => key.clone(),
         _ => {
-            println!("⚠️ No YouTube API key configured, using mock data");
-            return Ok(create_fallback_response(&request.query));
+            println!("⚠️ No YouTube API key configured");
+            return Err(AppError::MissingApiKey("YouTube API key is not configured".to_string()));
         }
     };
 
-    // 获取AI API密钥
-    let (ai_api_key, ai_provider) = match settings.ai_provider.as_str() {
-        "openai" => (settings.openai_api_key.clone(), "openai"),
-        "anthropic" => (settings.anthropic_api_key.clone(), "anthropic"),
-        _ => (settings.openai_api_key.clone(), "openai"),
+    // 获取AI提供商及其API密钥
+    let ai_provider = state.providers.resolve(&settings.ai_provider);
+    let screening = ScreeningContext {
+        config: ai_provider.config(&settings),
+        provider: ai_provider,
+        custom_prompt: settings.custom_filter_prompt.clone(),
+        prompt_context: PromptContext::from_settings(&settings, &filter_mode),
+        unverified_policy: settings.unverified_video_policy.clone(),
+        filter_mode: filter_mode.clone(),
+        skip_ai_analysis: request.skip_ai_analysis.unwrap_or(false),
+        duration: DurationBounds::from_settings(&settings),
+        ai_cache_days: settings.ai_cache_days,
     };
 
-    let ai_key = ai_api_key.unwrap_or_default();
-    let custom_prompt = settings.custom_filter_prompt.clone();
-    
-    drop(settings); // 释放锁
+    // 需要凑够的通过筛选的视频数，以及本次搜索允许消耗的配额
+    let target_count = request.max_results.unwrap_or(settings.video_count).max(1) as usize;
+    let quota_budget = settings.search_quota_budget.max(SEARCH_PAGE_QUOTA_COST);
+    let locale = SearchLocale::from_settings(&settings);
+    let cache_duration_hours = settings.cache_duration_hours;
+    let daily_quota = settings.youtube_daily_quota as i64;
+    let quota_exceeded_policy = settings.quota_exceeded_policy.clone();
+
+    // 超出每日配额预算时拒绝搜索，或退化为只使用缓存
+    let quota_exhausted = youtube_quota_exhausted(&state.db, state.clock.as_ref(), daily_quota).await;
+    if quota_exhausted && quota_exceeded_policy == "block" {
+        return Err(AppError::QuotaExceeded(format!(
+            "Daily YouTube quota budget of {} units has been used up",
+            daily_quota
+        )));
+    }
 
-    let max_results = request.max_results.unwrap_or(10).min(20);
+    // 第一页优先使用未过期的本地缓存，避免重复消耗配额和AI调用
+    if quota_exhausted || (request.page_token.is_none() && cache_duration_hours > 0) {
+        let (cached, cached_page_token) = if request.page_token.is_none() {
+            let cache_query = normalize_search_query(&request.query);
+            let cache_key = SearchCacheKey {
+                profile_id: profile.id,
+                query: &cache_query,
+                platform: platform.id(),
+                filter_mode: &filter_mode,
+            };
+            load_cached_search(&state.db, &cache_key)
+                .await
+                .unwrap_or_else(|e| {
+                    println!("⚠️ Failed to read search cache: {}", e);
+                    (vec![], None)
+                })
+        } else {
+            (vec![], None)
+        };
 
-    // 搜索YouTube视频（使用重试机制）
-    let mut videos = match search_youtube_videos_with_retry(&state.client, &request.query, &youtube_api_key, max_results, 3).await {
-        Ok(videos) => videos,
-        Err(e) => {
-            println!("❌ YouTube search failed after retries: {}", e);
-            return Ok(create_fallback_response(&request.query));
+        // 设置可能已经改变，重新应用确定性过滤和未验证视频策略
+        let cached = filter_videos_by_duration(prefilter_videos(cached), screening.duration);
+        let videos = filter_videos_by_mode(cached, &screening.filter_mode, &screening.unverified_policy);
+        if quota_exhausted || !videos.is_empty() {
+            let search_time = start_time.elapsed().as_secs_f64();
+            if quota_exhausted {
+                println!("⚠️ Daily YouTube quota used up, serving {} cached videos only", videos.len());
+            } else {
+                println!("💾 Serving {} cached videos for '{}' in {:.2}s", videos.len(), request.query, search_time);
+            }
+            return Ok(SearchResponse {
+                total_found: videos.len() as i32,
+                videos,
+                search_time,
+                ai_analysis_time: 0.0,
+                // 配额用完时无法继续翻页
+                next_page_token: if quota_exhausted { None } else { cached_page_token },
+            });
         }
-    };
-
-    let search_time = start_time.elapsed().as_secs_f64();
-    let ai_start_time = std::time::Instant::now();
+    }
 
-    // 检查是否跳过AI分析
-    if request.skip_ai_analysis.unwrap_or(false) {
+    if screening.skip_ai_analysis {
         println!("⚡ Skipping AI analysis for faster results (user requested)");
+    }
 
-        // 先计算总数
-        let total_found = videos.len() as i32;
-
-        // 保存搜索历史
-        batch_save_videos(videos.clone(), request.query.clone(), request.platform.clone(), state).await?;
+    let mut videos: Vec<Video> = Vec::new();
+    let mut seen_ids = std::collections::HashSet::new();
+    let mut page_tokens = locale.decode_page_token(request.page_token.as_deref());
+    let units_used = std::sync::atomic::AtomicI64::new(0);
+    let mut requests_total = 0;
+    let mut pages_fetched = 0;
+    let mut search_time = 0.0;
+    let mut ai_analysis_time = 0.0;
+
+    // 逐页搜索并筛选，直到凑够目标数量、没有下一页或配额预算用完
+    loop {
+        let page_start_time = std::time::Instant::now();
+
+        // 搜索YouTube视频（使用重试机制，多语言并行）
+        let api = YouTubeApi {
+            client: &state.client,
+            api_key: &youtube_api_key,
+            db: &state.db,
+            clock: state.clock.as_ref(),
+            units_used: &units_used,
+        };
+        let (page_videos, next_tokens, requests_sent) = match search_youtube_locales(
+            api,
+            platform,
+            screening.duration,
+            &request.query,
+            &locale,
+            page_tokens.as_ref(),
+        ).await {
+            Ok(page) => page,
+            Err(e) if pages_fetched == 0 => {
+                println!("❌ YouTube search failed after retries: {}", e);
+                return Err(AppError::classify(e, AppError::Network));
+            }
+            Err(e) => {
+                // 已有结果时保留当前页令牌，前端可以稍后从这里继续
+                println!("⚠️ YouTube search for page {} failed, returning collected results: {}", pages_fetched + 1, e);
+                break;
+            }
+        };
 
-        return Ok(SearchResponse {
-            videos,
-            total_found,
-            search_time,
-            ai_analysis_time: 0.0,
-        });
+        pages_fetched += 1;
+        // 以实际记录的配额为准（字幕请求数随结果数量变化）
+        let quota_used = units_used.load(std::sync::atomic::Ordering::Relaxed) as i32;
+        requests_total += requests_sent;
+        search_time += page_start_time.elapsed().as_secs_f64();
+
+        // 不同页之间可能出现重复视频
+        let page_videos: Vec<Video> = page_videos
+            .into_iter()
+            .filter(|video| seen_ids.insert(video.id.clone()))
+            .collect();
+
+        let ai_start_time = std::time::Instant::now();
+        let approved = screen_videos(&state, &screening, page_videos).await;
+        ai_analysis_time += ai_start_time.elapsed().as_secs_f64();
+
+        println!("📄 Page {}: {} videos passed screening ({} collected, quota used: {})",
+                 pages_fetched, approved.len(), videos.len() + approved.len(), quota_used);
+        videos.extend(approved);
+        page_tokens = Some(next_tokens);
+
+        let remaining_requests = page_tokens.as_ref().map_or(0, |tokens| tokens.len() as i32);
+        if videos.len() >= target_count || remaining_requests == 0 {
+            break;
+        }
+        // 按已完成请求的平均消耗估算下一页
+        let cost_per_request = if requests_total > 0 {
+            (quota_used + requests_total - 1) / requests_total
+        } else {
+            SEARCH_PAGE_QUOTA_COST
+        };
+        if quota_used + remaining_requests * cost_per_request > quota_budget {
+            println!("⚠️ Search quota budget of {} units reached after {} pages", quota_budget, pages_fetched);
+            break;
+        }
+        if youtube_quota_exhausted(&state.db, state.clock.as_ref(), daily_quota).await {
+            println!("⚠️ Daily YouTube quota used up after {} pages", pages_fetched);
+            break;
+        }
     }
 
+    let total_found = videos.len() as i32;
+
+    println!("✅ Found {} videos in {:.2}s (search: {:.2}s, AI: {:.2}s, pages: {})", 
+             total_found, start_time.elapsed().as_secs_f64(), search_time, ai_analysis_time, pages_fetched);
+
+    let next_page_token = page_tokens.and_then(|tokens| locale.encode_page_token(&tokens));
+
+    // Save search history
+    let cache_query = normalize_search_query(&request.query);
+    let cache_key = SearchCacheKey {
+        profile_id: profile.id,
+        query: &cache_query,
+        platform: platform.id(),
+        filter_mode: &filter_mode,
+    };
+    let pages = SearchPageTokens {
+        page_token: request.page_token.clone(),
+        next_page_token: next_page_token.clone(),
+    };
+    save_search_results(&state, &cache_key, &request.query, &videos, pages, true).await?;
+
+    Ok(SearchResponse {
+        videos,
+        total_found,
+        search_time,
+        ai_analysis_time,
+        next_page_token,
+    })
+}
+
+// 一次搜索中筛选视频所用的AI配置与筛选条件
+struct ScreeningContext {
+    provider: Arc<dyn AiProvider>,
+    config: ProviderConfig,
+    custom_prompt: Option<String>,
+    prompt_context: PromptContext,
+    unverified_policy: String,
+    filter_mode: String,
+    skip_ai_analysis: bool,
+    duration: DurationBounds,
+    ai_cache_days: i32,
+}
+
+// 对一页搜索结果进行AI分析并按筛选模式过滤
+async fn screen_videos(state: &AppState, screening: &ScreeningContext, videos: Vec<Video>) -> Vec<Video> {
+    // 先做确定性过滤，跳过AI分析时同样适用
+    let videos = prefilter_videos(videos);
+    let mut videos = filter_videos_by_duration(videos, screening.duration);
+    let ai_provider = &screening.provider;
+    let ai_config = &screening.config;
+
     // 对视频进行AI分析 - 使用批量分析优化性能
-    if !ai_key.is_empty() && !videos.is_empty() {
-        println!("🤖 Batch analyzing {} videos with {}", videos.len(), ai_provider);
-        
-        // Get duration settings
-        let settings = state.settings.lock().await;
-        let min_duration = Some(settings.min_duration);
-        let max_duration = Some(settings.max_duration);
-        let custom_prompt_ref = custom_prompt.clone();
-        drop(settings);
+    if !screening.skip_ai_analysis && ai_provider.is_configured(ai_config) && !videos.is_empty() {
+        println!("🤖 Batch analyzing {} videos with {}", videos.len(), ai_provider.id());
         
         // Try batch analysis first
-        let batch_result = match ai_provider {
-            "anthropic" => {
-                analyze_batch_with_anthropic(
-                    &state.client, 
-                    &ai_key, 
-                    &videos, 
-                    custom_prompt_ref.as_deref(),
-                    min_duration,
-                    max_duration
-                ).await
-            },
-            _ => {
-                analyze_batch_with_openai(
-                    &state.client, 
-                    &ai_key, 
-                    &videos, 
-                    custom_prompt_ref.as_deref(),
-                    min_duration,
-                    max_duration
-                ).await
-            },
+        let options = BatchAnalysisOptions {
+            custom_prompt: screening.custom_prompt.as_deref(),
+            context: &screening.prompt_context,
+            min_duration: Some(screening.duration.min_minutes),
+            max_duration: Some(screening.duration.max_minutes),
         };
+        let batch_result = analyze_batch_cached(
+            &state.db,
+            ai_provider.as_ref(),
+            &state.client,
+            ai_config,
+            &videos,
+            options,
+            screening.ai_cache_days,
+        ).await;
         
         match batch_result {
-            Ok(analyzed_videos) => {
-                println!("✅ Batch analysis successful: {} videos passed filtering", analyzed_videos.len());
-                videos = analyzed_videos;
+            Ok(outcome) => {
+                println!(
+                    "✅ Batch analysis successful: {} videos returned, {} invalid items, {} failed chunks",
+                    outcome.videos.len(),
+                    outcome.invalid_items.len(),
+                    outcome.failed_chunks.len()
+                );
+                videos = outcome.videos;
             },
             Err(e) => {
                 println!("⚠️ Batch analysis failed, falling back to individual analysis: {}", e);
@@ -1642,125 +4254,154 @@ async fn search_videos(
                 // Fallback to individual analysis
                 let analysis_futures: Vec<_> = videos.iter().map(|video| {
                     let client = &state.client;
-                    let api_key = ai_key.clone();
                     let title = video.title.clone();
                     let description = video.description.clone().unwrap_or_default();
-                    let custom_prompt_clone = custom_prompt.clone();
+                    let prompt_ref = screening.custom_prompt.as_deref();
+                    let context = &screening.prompt_context;
                     
                     async move {
-                        let prompt_ref = custom_prompt_clone.as_deref();
-                        match ai_provider {
-                            "anthropic" => analyze_with_anthropic(client, &api_key, &title, &description, prompt_ref).await,
-                            _ => analyze_with_openai(client, &api_key, &title, &description, prompt_ref).await,
-                        }
+                        ai_provider.analyze_video(client, ai_config, &title, &description, prompt_ref, context).await
                     }
                 }).collect();
 
-                let analysis_results = futures::future::join_all(analysis_futures).await;
+                let analysis_results: Vec<_> = futures::stream::iter(analysis_futures)
+                    .buffered(ai_config.concurrency())
+                    .collect()
+                    .await;
                 
                 // 应用AI分析结果
                 for (video, analysis_result) in videos.iter_mut().zip(analysis_results) {
-                    if let Ok(analysis) = analysis_result {
-                        video.ai_score = Some(analysis.overall_score);
-                        video.education_score = Some(analysis.education_score);
-                        video.safety_score = Some(analysis.safety_score);
-                        video.age_appropriate = Some(analysis.age_appropriate);
+                    match analysis_result {
+                        Ok(analysis) => {
+                            video.ai_score = Some(analysis.overall_score);
+                            video.education_score = Some(analysis.education_score);
+                            video.safety_score = Some(analysis.safety_score);
+                            video.age_appropriate = Some(analysis.age_appropriate);
+                            video.ai_reasoning = Some(analysis.reasoning);
+                            video.recommended_age = Some(analysis.recommended_age);
+                            video.analysis_status = Some(ANALYSIS_VERIFIED.to_string());
+                        },
+                        Err(e) => {
+                            println!("⚠️ Individual analysis failed for {}: {}", video.id, e);
+                            video.mark_unverified();
+                        }
                     }
                 }
             }
         }
     }
 
-    // 根据筛选模式过滤视频
-    videos = filter_videos_by_mode(videos, &request.filter_mode);
-
-    let ai_analysis_time = ai_start_time.elapsed().as_secs_f64();
-    let total_found = videos.len() as i32;
-
-    println!("✅ Found {} videos in {:.2}s (search: {:.2}s, AI: {:.2}s)", 
-             total_found, search_time + ai_analysis_time, search_time, ai_analysis_time);
+    // 没有得到有效AI评分的视频（跳过分析、未配置AI、请求失败或结果无法解析）明确标记为未验证
+    for video in videos.iter_mut().filter(|video| !video.is_verified()) {
+        video.mark_unverified();
+    }
 
-    // Save search history
-    batch_save_videos(videos.clone(), request.query, request.platform, state).await?;
+    // 根据筛选模式过滤视频，未验证视频的去留由策略决定
+    filter_videos_by_mode(videos, &screening.filter_mode, &screening.unverified_policy)
+}
 
-    Ok(SearchResponse {
-        videos,
-        total_found,
-        search_time,
-        ai_analysis_time,
-    })
+// 以当前设置为基础，使用请求中携带的密钥（若有）覆盖
+async fn request_provider_config(
+    provider: &dyn AiProvider,
+    api_key: &str,
+    state: &AppState,
+) -> ProviderConfig {
+    let mut config = provider.config(&*state.settings.lock().await);
+    if !api_key.is_empty() {
+        config.api_key = api_key.to_string();
+    }
+    config
 }
 
 #[command]
 async fn analyze_video(
     request: AIAnalysisRequest,
     state: tauri::State<'_, AppState>,
-) -> Result<AIAnalysisResponse, String> {
+) -> Result<AIAnalysisResponse, AppError> {
     println!("🤖 Production: Analyzing video '{}'", request.title);
     
-    if request.api_key.is_empty() {
-        return Err("API key is required for video analysis".to_string());
+    let provider = state.providers.resolve(&request.provider);
+    let config = request_provider_config(provider.as_ref(), &request.api_key, &state).await;
+    
+    if !provider.is_configured(&config) {
+        return Err(AppError::MissingApiKey("API key is required for video analysis".to_string()));
     }
 
     let description = request.description.unwrap_or_default();
-    let custom_prompt = request.filter_prompt.as_deref();
+    let custom_prompt = request.filter_prompt.clone();
+    let (_, settings) = profile_settings(&state, request.profile_id).await?;
+    let context = PromptContext::from_settings(&settings, &settings.default_filter_mode);
     
-    let result = match request.provider.as_str() {
-        "anthropic" => analyze_with_anthropic(&state.client, &request.api_key, &request.title, &description, custom_prompt).await,
-        _ => analyze_with_openai(&state.client, &request.api_key, &request.title, &description, custom_prompt).await,
-    };
+    let result = provider
+        .analyze_video(
+            &state.client,
+            &config,
+            &request.title,
+            &description,
+            custom_prompt.as_deref().or(settings.custom_filter_prompt.as_deref()),
+            &context,
+        )
+        .await;
 
-    result.map_err(|e| format!("AI analysis failed: {}", e))
+    result.map_err(|e| {
+        AppError::classify(e, |message| AppError::ProviderParse(format!("AI analysis failed: {}", message)))
+    })
 }
 
 #[command]
 async fn analyze_videos_batch(
     request: BatchAnalysisRequest,
     state: tauri::State<'_, AppState>,
-) -> Result<BatchAnalysisResponse, String> {
+) -> Result<BatchAnalysisResponse, AppError> {
     let start_time = std::time::Instant::now();
     println!("🤖 Production: Batch analyzing {} videos", request.videos.len());
     
-    if request.api_key.is_empty() {
-        return Err("API key is required for batch video analysis".to_string());
+    let provider = state.providers.resolve(&request.provider);
+    let config = request_provider_config(provider.as_ref(), &request.api_key, &state).await;
+    
+    if !provider.is_configured(&config) {
+        return Err(AppError::MissingApiKey("API key is required for batch video analysis".to_string()));
     }
 
-    if request.videos.is_empty() {
+    // 年龄限制或禁止嵌入的视频不发送给AI
+    let videos = prefilter_videos(request.videos);
+
+    if videos.is_empty() {
         return Ok(BatchAnalysisResponse {
             analyzed_videos: vec![],
             total_analyzed: 0,
             analysis_time: 0.0,
+            invalid_items: vec![],
+            failed_chunks: vec![],
         });
     }
 
-    let custom_prompt = request.filter_prompt.as_deref();
-    
-    let result = match request.provider.as_str() {
-        "anthropic" => {
-            analyze_batch_with_anthropic(
-                &state.client, 
-                &request.api_key, 
-                &request.videos, 
-                custom_prompt,
-                request.min_duration,
-                request.max_duration
-            ).await
-        },
-        _ => {
-            analyze_batch_with_openai(
-                &state.client, 
-                &request.api_key, 
-                &request.videos, 
-                custom_prompt,
-                request.min_duration,
-                request.max_duration
-            ).await
-        },
+    let (_, settings) = profile_settings(&state, request.profile_id).await?;
+    let mut context = PromptContext::from_settings(&settings, &settings.default_filter_mode);
+    context.min_duration = request.min_duration.unwrap_or(context.min_duration);
+    context.max_duration = request.max_duration.unwrap_or(context.max_duration);
+    let ai_cache_days = settings.ai_cache_days;
+    let options = BatchAnalysisOptions {
+        custom_prompt: request.filter_prompt.as_deref().or(settings.custom_filter_prompt.as_deref()),
+        context: &context,
+        min_duration: request.min_duration,
+        max_duration: request.max_duration,
     };
+    
+    let result = analyze_batch_cached(
+        &state.db,
+        provider.as_ref(),
+        &state.client,
+        &config,
+        &videos,
+        options,
+        ai_cache_days,
+    ).await;
 
     match result {
-        Ok(analyzed_videos) => {
+        Ok(outcome) => {
             let analysis_time = start_time.elapsed().as_secs_f64();
+            let analyzed_videos = outcome.videos;
             let total_analyzed = analyzed_videos.len() as i32;
             
             println!("✅ Batch analysis complete: {} videos analyzed in {:.2}s", 
@@ -1770,85 +4411,289 @@ async fn analyze_videos_batch(
                 analyzed_videos,
                 total_analyzed,
                 analysis_time,
+                invalid_items: outcome.invalid_items,
+                failed_chunks: outcome.failed_chunks,
             })
         },
         Err(e) => {
             println!("❌ Batch analysis failed: {}", e);
-            Err(format!("Batch AI analysis failed: {}", e))
+            Err(AppError::classify(e, |message| {
+                AppError::ProviderParse(format!("Batch AI analysis failed: {}", message))
+            }))
         }
     }
 }
 
-// Database operations
-#[command]
-async fn save_video(
-    video: Video,
-    state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
-    println!("💾 Production: Saving video '{}'", video.title);
-    
-    let video_json = serde_json::to_string(&video)
-        .map_err(|e| format!("Failed to serialize video: {}", e))?;
-    let cached_at = chrono::Utc::now().to_rfc3339();
-    let expires_at = (chrono::Utc::now() + chrono::Duration::hours(24)).to_rfc3339();
-    
+// 视频的列：v 为 videos 表，s 为同一档案在 profile_video_scores 中的评分（LEFT JOIN，可能为空）
+const VIDEO_COLUMNS: &str = "v.id, v.title, v.description, v.thumbnail_url, v.duration, v.channel_title, \
+    v.published_at, v.view_count, v.like_count, s.ai_score, s.education_score, s.safety_score, \
+    s.age_appropriate, v.tags, v.subtitles, s.analysis_status, s.ai_reasoning, s.recommended_age, \
+    s.age_fit_score, s.quality_score, v.platform, v.made_for_kids, v.age_restricted, v.embeddable, \
+    v.topic_categories";
+
+// 从 videos 表的一行还原 Video；查询中带有 cached_at 列时一并填充
+fn video_from_row(row: &SqliteRow) -> Result<Video, sqlx::Error> {
+    let topic_categories: Option<String> = row.try_get("topic_categories")?;
+    Ok(Video {
+        id: row.try_get("id")?,
+        title: row.try_get("title")?,
+        description: row.try_get("description")?,
+        thumbnail_url: row.try_get("thumbnail_url")?,
+        duration: row.try_get("duration")?,
+        channel_title: row.try_get("channel_title")?,
+        published_at: row.try_get("published_at")?,
+        view_count: row.try_get("view_count")?,
+        like_count: row.try_get("like_count")?,
+        ai_score: row.try_get("ai_score")?,
+        education_score: row.try_get("education_score")?,
+        safety_score: row.try_get("safety_score")?,
+        age_appropriate: row.try_get("age_appropriate")?,
+        tags: row.try_get("tags")?,
+        cached_at: row.try_get("cached_at").ok().flatten(),
+        subtitles: row.try_get("subtitles")?,
+        analysis_status: row.try_get("analysis_status")?,
+        ai_reasoning: row.try_get("ai_reasoning")?,
+        recommended_age: row.try_get("recommended_age")?,
+        age_fit_score: row.try_get("age_fit_score")?,
+        quality_score: row.try_get("quality_score")?,
+        platform: row.try_get("platform")?,
+        made_for_kids: row.try_get("made_for_kids")?,
+        age_restricted: row.try_get("age_restricted")?,
+        embeddable: row.try_get("embeddable")?,
+        topic_categories: topic_categories.and_then(|json| serde_json::from_str(&json).ok()),
+        warning: None,
+    })
+}
+
+// 写入或刷新视频元数据，收藏和历史引用同一行，因此总能看到最新数据
+// AI评分按档案保存，见 upsert_profile_video_scores
+async fn upsert_video(conn: &mut SqliteConnection, video: &Video) -> Result<(), sqlx::Error> {
+    let topic_categories = video
+        .topic_categories
+        .as_ref()
+        .and_then(|topics| serde_json::to_string(topics).ok());
+
     sqlx::query(
-        "INSERT OR REPLACE INTO cached_videos (id, query, platform, video_data, cached_at, expires_at) 
-         VALUES (?, ?, ?, ?, ?, ?)"
+        "INSERT INTO videos (
+            id, title, description, thumbnail_url, duration, channel_title, published_at,
+            view_count, like_count, tags, subtitles, platform, made_for_kids, age_restricted,
+            embeddable, topic_categories, updated_at
+         ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET
+            title = excluded.title,
+            description = excluded.description,
+            thumbnail_url = excluded.thumbnail_url,
+            duration = excluded.duration,
+            channel_title = excluded.channel_title,
+            published_at = excluded.published_at,
+            view_count = excluded.view_count,
+            like_count = excluded.like_count,
+            tags = excluded.tags,
+            subtitles = excluded.subtitles,
+            platform = excluded.platform,
+            made_for_kids = excluded.made_for_kids,
+            age_restricted = excluded.age_restricted,
+            embeddable = excluded.embeddable,
+            topic_categories = excluded.topic_categories,
+            updated_at = excluded.updated_at"
     )
     .bind(&video.id)
     .bind(&video.title)
-    .bind("youtube")
-    .bind(&video_json)
-    .bind(&cached_at)
-    .bind(&expires_at)
-    .execute(&*state.db)
-    .await
-    .map_err(|e| format!("Failed to save video: {}", e))?;
-    
-    Ok("Video saved successfully".to_string())
-}
+    .bind(&video.description)
+    .bind(&video.thumbnail_url)
+    .bind(video.duration)
+    .bind(&video.channel_title)
+    .bind(&video.published_at)
+    .bind(video.view_count)
+    .bind(video.like_count)
+    .bind(&video.tags)
+    .bind(&video.subtitles)
+    .bind(video.platform.as_deref().unwrap_or(Platform::YouTube.id()))
+    .bind(video.made_for_kids)
+    .bind(video.age_restricted)
+    .bind(video.embeddable)
+    .bind(topic_categories)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(&mut *conn)
+    .await?;
 
-#[command]
-async fn delete_video(
-    video_id: String,
-    state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
-    println!("🗑️ Production: Deleting video '{}'", video_id);
-    
-    sqlx::query("DELETE FROM cached_videos WHERE id = ?")
-        .bind(&video_id)
-        .execute(&*state.db)
-        .await
-        .map_err(|e| format!("Failed to delete video: {}", e))?;
-    
-    Ok("Video deleted successfully".to_string())
+    Ok(())
 }
 
+// 保存档案对视频的AI评分；未验证的结果不覆盖已有的有效评分
+async fn upsert_profile_video_scores(
+    conn: &mut SqliteConnection,
+    profile_id: i64,
+    video: &Video,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO profile_video_scores (
+            profile_id, video_id, ai_score, education_score, safety_score, age_appropriate,
+            analysis_status, ai_reasoning, recommended_age, age_fit_score, quality_score, updated_at
+         ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(profile_id, video_id) DO UPDATE SET
+            ai_score = excluded.ai_score,
+            education_score = excluded.education_score,
+            safety_score = excluded.safety_score,
+            age_appropriate = excluded.age_appropriate,
+            analysis_status = excluded.analysis_status,
+            ai_reasoning = excluded.ai_reasoning,
+            recommended_age = excluded.recommended_age,
+            age_fit_score = excluded.age_fit_score,
+            quality_score = excluded.quality_score,
+            updated_at = excluded.updated_at
+         WHERE excluded.analysis_status = ? OR profile_video_scores.analysis_status IS NOT ?"
+    )
+    .bind(profile_id)
+    .bind(&video.id)
+    .bind(video.ai_score)
+    .bind(video.education_score)
+    .bind(video.safety_score)
+    .bind(video.age_appropriate)
+    .bind(&video.analysis_status)
+    .bind(&video.ai_reasoning)
+    .bind(&video.recommended_age)
+    .bind(video.age_fit_score)
+    .bind(video.quality_score)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .bind(ANALYSIS_VERIFIED)
+    .bind(ANALYSIS_VERIFIED)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// 前端传来的视频只用于补全缺失的视频行：不覆盖后端写入的元数据，也不保存其中的AI评分
+async fn insert_video_if_missing(conn: &mut SqliteConnection, video: &Video) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT OR IGNORE INTO videos (id, title, description, thumbnail_url, duration, channel_title, platform, updated_at) 
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&video.id)
+    .bind(&video.title)
+    .bind(&video.description)
+    .bind(&video.thumbnail_url)
+    .bind(video.duration)
+    .bind(&video.channel_title)
+    .bind(video.platform.as_deref().unwrap_or(Platform::YouTube.id()))
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// 删除不再被缓存、收藏、搜索历史或观看记录引用的视频
+async fn purge_orphaned_videos(pool: &SqlitePool) -> Result<u64> {
+    let result = sqlx::query(
+        "DELETE FROM videos
+         WHERE id NOT IN (SELECT id FROM cached_videos)
+           AND id NOT IN (SELECT video_id FROM favorites)
+           AND id NOT IN (SELECT video_id FROM search_history_videos)
+           AND id NOT IN (SELECT video_id FROM watch_history)"
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query("DELETE FROM profile_video_scores WHERE video_id NOT IN (SELECT id FROM videos)")
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// Database operations
 #[command]
-async fn get_favorites(
+async fn save_video(
+    video: Video,
     state: tauri::State<'_, AppState>,
-) -> Result<Vec<FavoriteVideo>, String> {
-    println!("❤️ Production: Loading favorites");
+) -> Result<String, AppError> {
+    println!("💾 Production: Saving video '{}'", video.title);
+    
+    let profile = resolve_profile(&state.db, None).await?;
+    let cached_at = chrono::Utc::now().to_rfc3339();
+    let expires_at = cache_expires_at(state.settings.lock().await.cache_duration_hours);
+    
+    let mut tx = state.db.begin().await
+        .map_err(|e| AppError::Database(format!("Failed to begin transaction: {}", e)))?;
+    
+    insert_video_if_missing(&mut tx, &video)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to save video: {}", e)))?;
     
-    let rows = sqlx::query_as::<_, (i32, String, Option<String>, String, String)>(
-        "SELECT id, video_id, user_notes, created_at, video_data 
-         FROM favorites 
-         ORDER BY created_at DESC"
+    // 单独保存的视频不属于任何搜索，筛选模式留空，不会被搜索缓存命中
+    sqlx::query(
+        "INSERT OR REPLACE INTO cached_videos (profile_id, query, platform, filter_mode, id, cached_at, expires_at) 
+         VALUES (?, ?, ?, '', ?, ?, ?)"
     )
+    .bind(profile.id)
+    .bind(&video.title)
+    .bind(video.platform.as_deref().unwrap_or(Platform::YouTube.id()))
+    .bind(&video.id)
+    .bind(&cached_at)
+    .bind(&expires_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to save video: {}", e)))?;
+    
+    tx.commit().await
+        .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+    
+    Ok("Video saved successfully".to_string())
+}
+
+#[command]
+async fn delete_video(
+    video_id: String,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, AppError> {
+    println!("🗑️ Production: Deleting video '{}'", video_id);
+
+    require_parent(&state, parent_token.as_deref())?;
+    
+    sqlx::query("DELETE FROM cached_videos WHERE id = ?")
+        .bind(&video_id)
+        .execute(&*state.db)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to delete video: {}", e)))?;
+    
+    Ok("Video deleted successfully".to_string())
+}
+
+#[command]
+async fn get_favorites(
+    profile_id: Option<i64>,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<FavoriteVideo>, AppError> {
+    let (profile, _) = resolve_viewing_profile(&state, profile_id, parent_token.as_deref()).await?;
+    println!("❤️ Production: Loading favorites for profile '{}'", profile.name);
+    
+    let rows = sqlx::query(&format!(
+        "SELECT f.id AS favorite_id, f.profile_id, f.video_id, f.user_notes, f.created_at, {} 
+         FROM favorites f 
+         LEFT JOIN videos v ON v.id = f.video_id 
+         LEFT JOIN profile_video_scores s ON s.video_id = f.video_id AND s.profile_id = f.profile_id 
+         WHERE f.profile_id = ? 
+         ORDER BY f.created_at DESC",
+        VIDEO_COLUMNS
+    ))
+    .bind(profile.id)
     .fetch_all(&*state.db)
     .await
-    .map_err(|e| format!("Failed to load favorites: {}", e))?;
+    .map_err(|e| AppError::Database(format!("Failed to load favorites: {}", e)))?;
     
     let mut favorites = Vec::new();
-    for (id, video_id, user_notes, created_at, video_data) in rows {
-        let video: Option<Video> = serde_json::from_str(&video_data).ok();
+    for row in rows {
+        // 视频行缺失时 v.id 为 NULL，收藏仍然返回但不带视频信息
+        let has_video = row.try_get::<Option<String>, _>("id").ok().flatten().is_some();
         favorites.push(FavoriteVideo {
-            id,
-            video_id,
-            user_notes,
-            created_at,
-            video: video,
+            id: row.try_get("favorite_id")?,
+            profile_id: row.try_get("profile_id")?,
+            video_id: row.try_get("video_id")?,
+            user_notes: row.try_get("user_notes")?,
+            created_at: row.try_get("created_at")?,
+            video: if has_video { video_from_row(&row).ok() } else { None },
         });
     }
     
@@ -1859,57 +4704,44 @@ async fn get_favorites(
 async fn add_to_favorites(
     video_id: String,
     notes: Option<String>,
+    profile_id: Option<i64>,
+    parent_token: Option<String>,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
-    println!("❤️ Production: Adding {} to favorites", video_id);
+) -> Result<String, AppError> {
+    let (profile, _) = resolve_viewing_profile(&state, profile_id, parent_token.as_deref()).await?;
+    println!("❤️ Production: Adding {} to favorites of profile '{}'", video_id, profile.name);
+    
+    let created_at = chrono::Utc::now().to_rfc3339();
+    
+    let mut tx = state.db.begin().await
+        .map_err(|e| AppError::Database(format!("Failed to begin transaction: {}", e)))?;
     
-    // 首先尝试从缓存中获取视频数据
-    let video_row = sqlx::query_as::<_, (String,)>(
-        "SELECT video_data FROM cached_videos WHERE id = ?"
+    // 视频通常已在搜索时写入；如果没有，先占位，之后保存时会刷新为完整数据
+    sqlx::query(
+        "INSERT OR IGNORE INTO videos (id, title, platform, updated_at) VALUES (?, ?, ?, ?)"
     )
     .bind(&video_id)
-    .fetch_optional(&*state.db)
+    .bind("Unknown Video")
+    .bind(Platform::YouTube.id())
+    .bind(&created_at)
+    .execute(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to fetch video data: {}", e))?;
-    
-    let video_data = video_row
-        .map(|(data,)| data)
-        .unwrap_or_else(|| {
-            // 如果缓存中没有，创建一个基本的视频对象
-            let basic_video = Video {
-                id: video_id.clone(),
-                title: "Unknown Video".to_string(),
-                description: None,
-                thumbnail_url: None,
-                duration: None,
-                channel_title: None,
-                published_at: None,
-                view_count: None,
-                like_count: None,
-                ai_score: None,
-                education_score: None,
-                safety_score: None,
-                age_appropriate: None,
-                tags: None,
-                cached_at: Some(chrono::Utc::now().to_rfc3339()),
-                subtitles: None,
-            };
-            serde_json::to_string(&basic_video).unwrap_or_default()
-        });
-    
-    let created_at = chrono::Utc::now().to_rfc3339();
+    .map_err(|e| AppError::Database(format!("Failed to fetch video data: {}", e)))?;
     
     sqlx::query(
-        "INSERT OR REPLACE INTO favorites (video_id, user_notes, created_at, video_data) 
+        "INSERT OR REPLACE INTO favorites (profile_id, video_id, user_notes, created_at) 
          VALUES (?, ?, ?, ?)"
     )
+    .bind(profile.id)
     .bind(&video_id)
     .bind(&notes)
     .bind(&created_at)
-    .bind(&video_data)
-    .execute(&*state.db)
+    .execute(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to add to favorites: {}", e))?;
+    .map_err(|e| AppError::Database(format!("Failed to add to favorites: {}", e)))?;
+    
+    tx.commit().await
+        .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
     
     Ok("Added to favorites successfully".to_string())
 }
@@ -1917,94 +4749,359 @@ async fn add_to_favorites(
 #[command]
 async fn remove_from_favorites(
     favorite_id: i32,
+    parent_token: Option<String>,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     println!("🗑️ Production: Removing favorite {}", favorite_id);
+
+    require_parent(&state, parent_token.as_deref())?;
     
     sqlx::query("DELETE FROM favorites WHERE id = ?")
         .bind(favorite_id)
         .execute(&*state.db)
         .await
-        .map_err(|e| format!("Failed to remove from favorites: {}", e))?;
+        .map_err(|e| AppError::Database(format!("Failed to remove from favorites: {}", e)))?;
     
     Ok("Removed from favorites successfully".to_string())
 }
 
-// 批量保存视频
+// 批量保存视频（保存到当前档案）
+// 视频来自前端，其中的AI评分不可信，只写入缓存和搜索历史；评分由后端筛选时保存
 #[command]
 async fn batch_save_videos(
     videos: Vec<Video>,
     query: String,
     platform: String,
+    filter_mode: Option<String>,
+    pages: Option<SearchPageTokens>,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     println!("💾 Production: Batch saving {} videos", videos.len());
     
+    let profile = resolve_profile(&state.db, None).await?;
+    let filter_mode = filter_mode.unwrap_or_else(|| "balanced".to_string());
+    let cache_query = normalize_search_query(&query);
+    let cache_key = SearchCacheKey {
+        profile_id: profile.id,
+        query: &cache_query,
+        platform: &platform,
+        filter_mode: &filter_mode,
+    };
+    let count = save_search_results(&state, &cache_key, &query, &videos, pages.unwrap_or_default(), false).await?;
+    
+    Ok(format!("Successfully saved {} videos", count))
+}
+
+// 写入一次搜索的结果：视频、搜索缓存和搜索历史
+// 只有后端筛选得到的结果（screened 为 true）才保存元数据和AI评分，其他来源只补全缺失的视频行
+async fn save_search_results(
+    state: &AppState,
+    cache_key: &SearchCacheKey<'_>,
+    query: &str,
+    videos: &[Video],
+    pages: SearchPageTokens,
+    screened: bool,
+) -> Result<usize, AppError> {
     let cached_at = chrono::Utc::now().to_rfc3339();
-    let expires_at = (chrono::Utc::now() + chrono::Duration::hours(24)).to_rfc3339();
+    let expires_at = cache_expires_at(state.settings.lock().await.cache_duration_hours);
     let count = videos.len();
     
     // 开始事务
     let mut tx = state.db.begin().await
-        .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        .map_err(|e| AppError::Database(format!("Failed to begin transaction: {}", e)))?;
     
     for video in videos {
-        let video_json = serde_json::to_string(&video)
-            .map_err(|e| format!("Failed to serialize video: {}", e))?;
-        
-        sqlx::query(
-            "INSERT OR REPLACE INTO cached_videos (id, query, platform, video_data, cached_at, expires_at) 
-             VALUES (?, ?, ?, ?, ?, ?)"
-        )
-        .bind(&video.id)
-        .bind(&query)
-        .bind(&platform)
-        .bind(&video_json)
-        .bind(&cached_at)
-        .bind(&expires_at)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to save video: {}", e))?;
+        if screened {
+            upsert_video(&mut tx, video)
+                .await
+                .map_err(|e| AppError::Database(format!("Failed to save video: {}", e)))?;
+            upsert_profile_video_scores(&mut tx, cache_key.profile_id, video)
+                .await
+                .map_err(|e| AppError::Database(format!("Failed to save video: {}", e)))?;
+        } else {
+            insert_video_if_missing(&mut tx, video)
+                .await
+                .map_err(|e| AppError::Database(format!("Failed to save video: {}", e)))?;
+        }
     }
+
+    save_search_cache(
+        &mut tx,
+        cache_key,
+        videos,
+        pages.page_token.as_deref(),
+        pages.next_page_token.as_deref(),
+        &cached_at,
+        &expires_at,
+    )
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to save search cache: {}", e)))?;
     
-    // 保存搜索历史
-    sqlx::query(
-        "INSERT INTO search_history (query, platform, filter_mode, results_count, created_at) 
-         VALUES (?, ?, ?, ?, ?)"
+    // 保存搜索历史及其结果列表
+    let history_id = sqlx::query(
+        "INSERT INTO search_history (profile_id, query, platform, filter_mode, results_count, created_at) 
+         VALUES (?, ?, ?, ?, ?, ?)"
     )
-    .bind(&query)
-    .bind(&platform)
-    .bind("balanced")
+    .bind(cache_key.profile_id)
+    .bind(query)
+    .bind(cache_key.platform)
+    .bind(cache_key.filter_mode)
     .bind(count as i32)
     .bind(&cached_at)
     .execute(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to save search history: {}", e))?;
+    .map_err(|e| AppError::Database(format!("Failed to save search history: {}", e)))?
+    .last_insert_rowid();
+    
+    for (position, video) in videos.iter().enumerate() {
+        sqlx::query(
+            "INSERT OR IGNORE INTO search_history_videos (history_id, video_id, position) 
+             VALUES (?, ?, ?)"
+        )
+        .bind(history_id)
+        .bind(&video.id)
+        .bind(position as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to save search history: {}", e)))?;
+    }
     
     // 提交事务
     tx.commit().await
-        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
     
-    Ok(format!("Successfully saved {} videos", count))
+    Ok(count)
+}
+
+// 搜索缓存按规范化后的查询词匹配：去掉首尾空白、合并连续空白并转为小写
+fn normalize_search_query(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// 保存的结果所在的页（page_token 为空表示第一页）及其后一页的令牌
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchPageTokens {
+    pub page_token: Option<String>,
+    pub next_page_token: Option<String>,
+}
+
+// 一次搜索在缓存中的键，query 为规范化后的查询词
+struct SearchCacheKey<'a> {
+    profile_id: i64,
+    query: &'a str,
+    platform: &'a str,
+    filter_mode: &'a str,
+}
+
+// 把一页搜索结果写入缓存
+// 第一页（page_token 为空）替换该搜索之前的缓存；后续页只有紧接在已缓存的页之后才追加，
+// 保证缓存始终是从第一页开始的连续结果，next_page_token 指向缓存之后的下一页
+async fn save_search_cache(
+    conn: &mut SqliteConnection,
+    key: &SearchCacheKey<'_>,
+    videos: &[Video],
+    page_token: Option<&str>,
+    next_page_token: Option<&str>,
+    cached_at: &str,
+    expires_at: &str,
+) -> Result<()> {
+    match page_token {
+        None => {
+            sqlx::query(
+                "DELETE FROM cached_videos WHERE profile_id = ? AND query = ? AND platform = ? AND filter_mode = ?"
+            )
+            .bind(key.profile_id)
+            .bind(key.query)
+            .bind(key.platform)
+            .bind(key.filter_mode)
+            .execute(&mut *conn)
+            .await?;
+        }
+        Some(page_token) => {
+            let cached_token: Option<(Option<String>,)> = sqlx::query_as(
+                "SELECT next_page_token FROM cached_searches
+                 WHERE profile_id = ? AND query = ? AND platform = ? AND filter_mode = ? AND expires_at > ?"
+            )
+            .bind(key.profile_id)
+            .bind(key.query)
+            .bind(key.platform)
+            .bind(key.filter_mode)
+            .bind(cached_at)
+            .fetch_optional(&mut *conn)
+            .await?;
+            if cached_token.and_then(|(token,)| token).as_deref() != Some(page_token) {
+                return Ok(());
+            }
+        }
+    }
+
+    let (next_position,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM cached_videos
+         WHERE profile_id = ? AND query = ? AND platform = ? AND filter_mode = ?"
+    )
+    .bind(key.profile_id)
+    .bind(key.query)
+    .bind(key.platform)
+    .bind(key.filter_mode)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    for (offset, video) in videos.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO cached_videos (profile_id, query, platform, filter_mode, id, position, cached_at, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(profile_id, query, platform, filter_mode, id) DO NOTHING"
+        )
+        .bind(key.profile_id)
+        .bind(key.query)
+        .bind(key.platform)
+        .bind(key.filter_mode)
+        .bind(&video.id)
+        .bind(next_position + offset as i64)
+        .bind(cached_at)
+        .bind(expires_at)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // 已缓存的各页与整个搜索一起过期
+    sqlx::query(
+        "UPDATE cached_videos SET expires_at = ?
+         WHERE profile_id = ? AND query = ? AND platform = ? AND filter_mode = ?"
+    )
+    .bind(expires_at)
+    .bind(key.profile_id)
+    .bind(key.query)
+    .bind(key.platform)
+    .bind(key.filter_mode)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO cached_searches (profile_id, query, platform, filter_mode, next_page_token, cached_at, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(key.profile_id)
+    .bind(key.query)
+    .bind(key.platform)
+    .bind(key.filter_mode)
+    .bind(next_page_token)
+    .bind(cached_at)
+    .bind(expires_at)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// 查找同一档案、查询词、平台和筛选模式下未过期的缓存结果（保留保存时的AI评分）
+// 同时返回缓存之后的下一页令牌
+async fn load_cached_search(
+    pool: &SqlitePool,
+    key: &SearchCacheKey<'_>,
+) -> Result<(Vec<Video>, Option<String>)> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let search: Option<(Option<String>,)> = sqlx::query_as(
+        "SELECT next_page_token FROM cached_searches
+         WHERE profile_id = ? AND query = ? AND platform = ? AND filter_mode = ? AND expires_at > ?"
+    )
+    .bind(key.profile_id)
+    .bind(key.query)
+    .bind(key.platform)
+    .bind(key.filter_mode)
+    .bind(&now)
+    .fetch_optional(pool)
+    .await?;
+    let Some((next_page_token,)) = search else {
+        return Ok((vec![], None));
+    };
+
+    let rows = sqlx::query(&format!(
+        "SELECT {}, c.cached_at FROM cached_videos c
+         JOIN videos v ON v.id = c.id
+         LEFT JOIN profile_video_scores s ON s.video_id = c.id AND s.profile_id = c.profile_id
+         WHERE c.profile_id = ? AND c.query = ? AND c.platform = ? AND c.filter_mode = ? AND c.expires_at > ?
+         ORDER BY c.position",
+        VIDEO_COLUMNS
+    ))
+    .bind(key.profile_id)
+    .bind(key.query)
+    .bind(key.platform)
+    .bind(key.filter_mode)
+    .bind(&now)
+    .fetch_all(pool)
+    .await?;
+
+    let videos = rows
+        .iter()
+        .filter_map(|row| video_from_row(row).ok())
+        .collect();
+    Ok((videos, next_page_token))
+}
+
+// 缓存过期时间
+fn cache_expires_at(cache_duration_hours: i32) -> String {
+    (chrono::Utc::now() + chrono::Duration::hours(cache_duration_hours.max(0) as i64)).to_rfc3339()
+}
+
+// 删除过期的缓存视频（走 idx_cached_videos_expires 索引）
+async fn purge_expired_videos(pool: &SqlitePool) -> Result<u64> {
+    let now = chrono::Utc::now().to_rfc3339();
+    sqlx::query("DELETE FROM cached_searches WHERE expires_at <= ?")
+        .bind(&now)
+        .execute(pool)
+        .await?;
+    let result = sqlx::query("DELETE FROM cached_videos WHERE expires_at <= ?")
+        .bind(&now)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// 后台定期清理过期缓存
+const CACHE_PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+async fn run_cache_purge_task(pool: Arc<SqlitePool>) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(CACHE_PURGE_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        match purge_expired_videos(&pool).await {
+            Ok(0) => {}
+            Ok(purged) => println!("🧹 Purged {} expired cached videos", purged),
+            Err(e) => println!("⚠️ Failed to purge expired cached videos: {}", e),
+        }
+        if let Err(e) = purge_orphaned_videos(&pool).await {
+            println!("⚠️ Failed to purge orphaned videos: {}", e);
+        }
+    }
 }
 
 // 获取所有缓存的视频
 #[command]
 async fn get_cached_videos(
     state: tauri::State<'_, AppState>,
-) -> Result<Vec<Video>, String> {
+) -> Result<Vec<Video>, AppError> {
     println!("📚 Production: Loading all cached videos from database");
 
-    let rows = sqlx::query_as::<_, (String,)>(
-        "SELECT video_data FROM cached_videos ORDER BY cached_at DESC"
-    )
+    // 评分因档案而异，只返回当前档案的缓存
+    let profile = resolve_profile(&state.db, None).await?;
+    let rows = sqlx::query(&format!(
+        "SELECT {}, MAX(c.cached_at) AS cached_at FROM cached_videos c
+         JOIN videos v ON v.id = c.id
+         LEFT JOIN profile_video_scores s ON s.video_id = c.id AND s.profile_id = c.profile_id
+         WHERE c.profile_id = ?
+         GROUP BY v.id
+         ORDER BY MAX(c.cached_at) DESC",
+        VIDEO_COLUMNS
+    ))
+    .bind(profile.id)
     .fetch_all(&*state.db)
     .await
-    .map_err(|e| format!("Failed to load cached videos: {}", e))?;
+    .map_err(|e| AppError::Database(format!("Failed to load cached videos: {}", e)))?;
 
     let mut videos = Vec::new();
-    for (video_data,) in rows {
-        if let Ok(video) = serde_json::from_str::<Video>(&video_data) {
+    for row in &rows {
+        if let Ok(video) = video_from_row(row) {
             videos.push(video);
         }
     }
@@ -2016,59 +5113,1330 @@ async fn get_cached_videos(
 // 清除所有缓存的视频
 #[command]
 async fn clear_cache(
+    parent_token: Option<String>,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     println!("🗑️ Production: Clearing all cached videos");
 
+    require_parent(&state, parent_token.as_deref())?;
+
+    sqlx::query("DELETE FROM cached_searches")
+        .execute(&*state.db)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to clear cache: {}", e)))?;
     let result = sqlx::query("DELETE FROM cached_videos")
         .execute(&*state.db)
         .await
-        .map_err(|e| format!("Failed to clear cache: {}", e))?;
+        .map_err(|e| AppError::Database(format!("Failed to clear cache: {}", e)))?;
+    purge_orphaned_videos(&state.db)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to clear cache: {}", e)))?;
 
     Ok(format!("Cleared {} cached videos", result.rows_affected()))
 }
 
-// 获取搜索历史
+// 获取当日YouTube配额使用情况
+#[command]
+async fn get_quota_usage(
+    state: tauri::State<'_, AppState>,
+) -> Result<QuotaUsage, AppError> {
+    println!("📊 Production: Loading YouTube quota usage");
+
+    let (daily_budget, exceeded_policy) = {
+        let settings = state.settings.lock().await;
+        (settings.youtube_daily_quota as i64, settings.quota_exceeded_policy.clone())
+    };
+    let date = quota_date(state.clock.as_ref());
+
+    let rows = sqlx::query_as::<_, (String, i64, i64)>(
+        "SELECT endpoint, COUNT(*), SUM(units) 
+         FROM youtube_quota_usage 
+         WHERE quota_date = ? 
+         GROUP BY endpoint 
+         ORDER BY SUM(units) DESC"
+    )
+    .bind(&date)
+    .fetch_all(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to load quota usage: {}", e)))?;
+
+    let endpoints: Vec<EndpointQuotaUsage> = rows
+        .into_iter()
+        .map(|(endpoint, calls, units)| EndpointQuotaUsage { endpoint, calls, units })
+        .collect();
+    let used_units: i64 = endpoints.iter().map(|e| e.units).sum();
+
+    Ok(QuotaUsage {
+        date,
+        used_units,
+        daily_budget,
+        remaining_units: (daily_budget - used_units).max(0),
+        exceeded_policy,
+        endpoints,
+    })
+}
+
+// 获取搜索历史
+#[command]
+async fn get_search_history(
+    limit: Option<i32>,
+    profile_id: Option<i64>,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<SearchHistoryEntry>, AppError> {
+    let (profile, _) = resolve_viewing_profile(&state, profile_id, parent_token.as_deref()).await?;
+    println!("📚 Production: Loading search history for profile '{}'", profile.name);
+    
+    let limit_value = limit.unwrap_or(20);
+    
+    let rows = sqlx::query_as::<_, (i64, i64, String, String, String, i32, String)>(
+        "SELECT id, profile_id, query, platform, filter_mode, results_count, created_at 
+         FROM search_history 
+         WHERE profile_id = ? 
+         ORDER BY created_at DESC 
+         LIMIT ?"
+    )
+    .bind(profile.id)
+    .bind(limit_value)
+    .fetch_all(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to load search history: {}", e)))?;
+    
+    let history = rows.into_iter().map(|(id, profile_id, query, platform, filter_mode, results_count, created_at)| {
+        SearchHistoryEntry {
+            id,
+            profile_id,
+            query,
+            platform,
+            filter_mode,
+            results_count,
+            created_at,
+        }
+    }).collect();
+    
+    Ok(history)
+}
+
+const PROFILE_COLUMNS: &str = "id, name, child_age, custom_filter_prompt, min_duration, max_duration, \
+    filter_mode, unverified_video_policy, daily_screen_time_minutes, is_active, created_at, updated_at";
+
+fn profile_from_row(row: &SqliteRow) -> Result<ChildProfile, sqlx::Error> {
+    Ok(ChildProfile {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        child_age: row.try_get("child_age")?,
+        custom_filter_prompt: row.try_get("custom_filter_prompt")?,
+        min_duration: row.try_get("min_duration")?,
+        max_duration: row.try_get("max_duration")?,
+        filter_mode: row.try_get("filter_mode")?,
+        unverified_video_policy: row.try_get("unverified_video_policy")?,
+        daily_screen_time_minutes: row.try_get("daily_screen_time_minutes")?,
+        is_active: row.try_get("is_active")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+// 按ID查找档案，未指定时返回当前激活的档案
+async fn resolve_profile(pool: &SqlitePool, profile_id: Option<i64>) -> Result<ChildProfile, AppError> {
+    let row = match profile_id {
+        Some(id) => {
+            sqlx::query(&format!("SELECT {} FROM profiles WHERE id = ?", PROFILE_COLUMNS))
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+        }
+        None => {
+            sqlx::query(&format!("SELECT {} FROM profiles WHERE is_active = 1 ORDER BY id LIMIT 1", PROFILE_COLUMNS))
+                .fetch_optional(pool)
+                .await
+        }
+    }
+    .map_err(|e| AppError::Database(format!("Failed to load profile: {}", e)))?;
+
+    match (row, profile_id) {
+        (Some(row), _) => Ok(profile_from_row(&row)?),
+        (None, Some(id)) => Err(AppError::InvalidInput(format!("Profile {} does not exist", id))),
+        (None, None) => Err(AppError::InvalidInput("No active profile".to_string())),
+    }
+}
+
+// 档案及叠加了档案覆盖项的设置
+async fn profile_settings(state: &AppState, profile_id: Option<i64>) -> Result<(ChildProfile, AppSettings), AppError> {
+    let profile = resolve_profile(&state.db, profile_id).await?;
+    let settings = profile.apply_to(&*state.settings.lock().await);
+    Ok((profile, settings))
+}
+
+// 观看、收藏和搜索历史相关命令使用的档案：默认是当前激活的档案
+// 切换档案需要家长PIN，因此指定其他档案（换用别的观看时长、时间表，或查看别人的收藏）同样需要家长解锁
+async fn resolve_viewing_profile(
+    state: &AppState,
+    profile_id: Option<i64>,
+    parent_token: Option<&str>,
+) -> Result<(ChildProfile, AppSettings), AppError> {
+    let (active, settings) = profile_settings(state, None).await?;
+    match profile_id {
+        Some(id) if id != active.id => {
+            require_parent(state, parent_token)?;
+            profile_settings(state, Some(id)).await
+        }
+        _ => Ok((active, settings)),
+    }
+}
+
+// 获取所有儿童档案
+#[command]
+async fn list_profiles(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ChildProfile>, AppError> {
+    println!("👶 Production: Loading profiles");
+
+    let rows = sqlx::query(&format!("SELECT {} FROM profiles ORDER BY id", PROFILE_COLUMNS))
+        .fetch_all(&*state.db)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to load profiles: {}", e)))?;
+
+    Ok(rows.iter().map(profile_from_row).collect::<Result<Vec<_>, _>>()?)
+}
+
+#[command]
+async fn get_active_profile(
+    state: tauri::State<'_, AppState>,
+) -> Result<ChildProfile, AppError> {
+    resolve_profile(&state.db, None).await
+}
+
+#[command]
+async fn create_profile(
+    profile: ProfileInput,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ChildProfile, AppError> {
+    println!("👶 Production: Creating profile '{}'", profile.name);
+
+    require_parent(&state, parent_token.as_deref())?;
+
+    profile
+        .validate()
+        .map_err(|e| AppError::InvalidInput(format!("Invalid profile: {}", e)))?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let profile_id = sqlx::query(
+        "INSERT INTO profiles (name, child_age, custom_filter_prompt, min_duration, max_duration, 
+                               filter_mode, unverified_video_policy, daily_screen_time_minutes, 
+                               is_active, created_at, updated_at) 
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, 0, ?, ?)"
+    )
+    .bind(profile.name.trim())
+    .bind(&profile.child_age)
+    .bind(&profile.custom_filter_prompt)
+    .bind(profile.min_duration)
+    .bind(profile.max_duration)
+    .bind(&profile.filter_mode)
+    .bind(&profile.unverified_video_policy)
+    .bind(profile.daily_screen_time_minutes)
+    .bind(&now)
+    .bind(&now)
+    .execute(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to create profile: {}", e)))?
+    .last_insert_rowid();
+
+    resolve_profile(&state.db, Some(profile_id)).await
+}
+
+#[command]
+async fn update_profile(
+    profile_id: i64,
+    profile: ProfileInput,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ChildProfile, AppError> {
+    println!("👶 Production: Updating profile {}", profile_id);
+
+    require_parent(&state, parent_token.as_deref())?;
+
+    profile
+        .validate()
+        .map_err(|e| AppError::InvalidInput(format!("Invalid profile: {}", e)))?;
+
+    let result = sqlx::query(
+        "UPDATE profiles 
+         SET name = ?, child_age = ?, custom_filter_prompt = ?, min_duration = ?, max_duration = ?, 
+             filter_mode = ?, unverified_video_policy = ?, daily_screen_time_minutes = ?, updated_at = ? 
+         WHERE id = ?"
+    )
+    .bind(profile.name.trim())
+    .bind(&profile.child_age)
+    .bind(&profile.custom_filter_prompt)
+    .bind(profile.min_duration)
+    .bind(profile.max_duration)
+    .bind(&profile.filter_mode)
+    .bind(&profile.unverified_video_policy)
+    .bind(profile.daily_screen_time_minutes)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .bind(profile_id)
+    .execute(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to update profile: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::InvalidInput(format!("Profile {} does not exist", profile_id)));
+    }
+    resolve_profile(&state.db, Some(profile_id)).await
+}
+
+// 删除档案及其收藏、搜索历史、观看记录和缓存；删除的是当前档案时激活剩余的第一个档案
+#[command]
+async fn delete_profile(
+    profile_id: i64,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, AppError> {
+    println!("🗑️ Production: Deleting profile {}", profile_id);
+
+    require_parent(&state, parent_token.as_deref())?;
+
+    let profile = resolve_profile(&state.db, Some(profile_id)).await?;
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM profiles")
+        .fetch_one(&*state.db)
+        .await?;
+    if count <= 1 {
+        return Err(AppError::InvalidInput("Cannot delete the last profile".to_string()));
+    }
+
+    let mut tx = state.db.begin().await
+        .map_err(|e| AppError::Database(format!("Failed to begin transaction: {}", e)))?;
+    for sql in [
+        "DELETE FROM favorites WHERE profile_id = ?",
+        "DELETE FROM search_history WHERE profile_id = ?",
+        "DELETE FROM cached_videos WHERE profile_id = ?",
+        "DELETE FROM cached_searches WHERE profile_id = ?",
+        "DELETE FROM profile_video_scores WHERE profile_id = ?",
+        "DELETE FROM watch_history WHERE profile_id = ?",
+        "DELETE FROM profiles WHERE id = ?",
+    ] {
+        sqlx::query(sql)
+            .bind(profile_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete profile: {}", e)))?;
+    }
+    if profile.is_active {
+        sqlx::query("UPDATE profiles SET is_active = 1 WHERE id = (SELECT MIN(id) FROM profiles)")
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete profile: {}", e)))?;
+    }
+    tx.commit().await
+        .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+
+    Ok(format!("Deleted profile '{}'", profile.name))
+}
+
+// 切换当前激活的档案，未指定档案的命令都使用该档案
+#[command]
+async fn switch_profile(
+    profile_id: i64,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ChildProfile, AppError> {
+    require_parent(&state, parent_token.as_deref())?;
+
+    let profile = resolve_profile(&state.db, Some(profile_id)).await?;
+    println!("👶 Production: Switching to profile '{}'", profile.name);
+
+    sqlx::query("UPDATE profiles SET is_active = (id = ?)")
+        .bind(profile_id)
+        .execute(&*state.db)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to switch profile: {}", e)))?;
+
+    resolve_profile(&state.db, None).await
+}
+
+// 观看时长统计：前端开始/停止播放时通知后端，后台任务定期累计并推送事件
+// 计时在后端进行，重新加载页面不会清零；用完每日额度后拒绝搜索和新的播放
+const SCREEN_TIME_TICK_SECS: u64 = 5;
+const SCREEN_TIME_WARNING_EVENT: &str = "screen-time-warning";
+const SCREEN_TIME_COUNTDOWN_EVENT: &str = "screen-time-countdown";
+const SCREEN_TIME_LOCKOUT_EVENT: &str = "screen-time-lockout";
+
+struct ActiveWatchSession {
+    profile_id: i64,
+    last_tick: std::time::Instant,
+    warned: bool,
+}
+
+impl ActiveWatchSession {
+    // 取出自上次累计以来经过的整秒数，不足一秒的部分留到下次
+    fn take_elapsed(&mut self) -> i64 {
+        let seconds = self.last_tick.elapsed().as_secs();
+        self.last_tick += std::time::Duration::from_secs(seconds);
+        seconds as i64
+    }
+}
+
+// 正在进行的观看会话，键为 watch_sessions.id
+#[derive(Default)]
+struct ScreenTimeTracker {
+    sessions: HashMap<i64, ActiveWatchSession>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScreenTimeStatus {
+    pub profile_id: i64,
+    pub date: String, // 档案时间表所用时区的日期
+    pub used_seconds: i64,
+    pub limit_seconds: Option<i64>, // 为空表示不限制
+    pub remaining_seconds: Option<i64>,
+    pub locked: bool,
+    pub session_id: Option<i64>,
+}
+
+// 观看时长按档案时间表所用时区的本地日期统计，与时间表的判断使用同一时钟和时区
+async fn screen_time_date(state: &AppState, profile_id: i64) -> Result<String, AppError> {
+    let (schedule, _) = load_viewing_schedule(&state.db, profile_id).await?;
+    schedule
+        .local_date(state.clock.now())
+        .map_err(|e| AppError::InvalidInput(format!("Invalid viewing schedule: {}", e)))
+}
+
+async fn screen_time_status(
+    state: &AppState,
+    profile: &ChildProfile,
+    settings: &AppSettings,
+) -> Result<ScreenTimeStatus, AppError> {
+    let date = screen_time_date(state, profile.id).await?;
+    let used_seconds = sqlx::query_as::<_, (i64,)>(
+        "SELECT watched_seconds FROM screen_time_daily WHERE profile_id = ? AND usage_date = ?"
+    )
+    .bind(profile.id)
+    .bind(&date)
+    .fetch_optional(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to load screen time: {}", e)))?
+    .map(|(seconds,)| seconds)
+    .unwrap_or(0);
+
+    let limit_seconds = (settings.daily_screen_time_minutes > 0)
+        .then(|| settings.daily_screen_time_minutes as i64 * 60);
+    let remaining_seconds = limit_seconds.map(|limit| (limit - used_seconds).max(0));
+
+    Ok(ScreenTimeStatus {
+        profile_id: profile.id,
+        date,
+        used_seconds,
+        limit_seconds,
+        remaining_seconds,
+        locked: remaining_seconds == Some(0),
+        session_id: None,
+    })
+}
+
+// 每日额度用完时拒绝继续
+async fn ensure_screen_time_available(
+    state: &AppState,
+    profile: &ChildProfile,
+    settings: &AppSettings,
+) -> Result<ScreenTimeStatus, AppError> {
+    let status = screen_time_status(state, profile, settings).await?;
+    if status.locked {
+        return Err(AppError::ScreenTimeExceeded(format!(
+            "Today's screen time of {} minutes for '{}' has been used up",
+            settings.daily_screen_time_minutes, profile.name
+        )));
+    }
+    Ok(status)
+}
+
+async fn add_watch_seconds(state: &AppState, session_id: i64, profile_id: i64, seconds: i64) -> Result<()> {
+    let date = screen_time_date(state, profile_id).await?;
+    let mut tx = state.db.begin().await?;
+    sqlx::query(
+        "UPDATE watch_sessions SET watched_seconds = watched_seconds + ?, last_heartbeat_at = ? WHERE id = ?"
+    )
+    .bind(seconds)
+    .bind(state.clock.now().to_rfc3339())
+    .bind(session_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "INSERT INTO screen_time_daily (profile_id, usage_date, watched_seconds) VALUES (?, ?, ?)
+         ON CONFLICT(profile_id, usage_date) DO UPDATE SET watched_seconds = watched_seconds + excluded.watched_seconds"
+    )
+    .bind(profile_id)
+    .bind(&date)
+    .bind(seconds)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// 累计剩余的时长并结束会话
+async fn finish_watch_session(state: &AppState, session_id: i64) -> Result<()> {
+    let elapsed = state
+        .screen_time
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .sessions
+        .remove(&session_id)
+        .map(|mut session| (session.profile_id, session.take_elapsed()));
+
+    if let Some((profile_id, seconds)) = elapsed {
+        if seconds > 0 {
+            add_watch_seconds(state, session_id, profile_id, seconds).await?;
+        }
+    }
+    sqlx::query("UPDATE watch_sessions SET ended_at = ? WHERE id = ? AND ended_at IS NULL")
+        .bind(state.clock.now().to_rfc3339())
+        .bind(session_id)
+        .execute(&*state.db)
+        .await?;
+    Ok(())
+}
+
+async fn run_screen_time_task(app: AppHandle) {
+    let state = app.state::<AppState>();
+
+    // 应用异常退出时遗留的会话以最后一次累计的时间结束
+    if let Err(e) = sqlx::query("UPDATE watch_sessions SET ended_at = last_heartbeat_at WHERE ended_at IS NULL")
+        .execute(&*state.db)
+        .await
+    {
+        println!("⚠️ Failed to close stale watch sessions: {}", e);
+    }
+
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(SCREEN_TIME_TICK_SECS));
+    loop {
+        interval.tick().await;
+        screen_time_tick(&app, &state).await;
+    }
+}
+
+async fn screen_time_tick(app: &AppHandle, state: &AppState) {
+    let elapsed: Vec<(i64, i64, i64)> = state
+        .screen_time
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .sessions
+        .iter_mut()
+        .map(|(session_id, session)| (*session_id, session.profile_id, session.take_elapsed()))
+        .collect();
+
+    for (session_id, profile_id, seconds) in elapsed {
+        if seconds > 0 {
+            if let Err(e) = add_watch_seconds(state, session_id, profile_id, seconds).await {
+                println!("⚠️ Failed to record screen time: {}", e);
+                continue;
+            }
+        }
+
+        let (profile, settings) = match profile_settings(state, Some(profile_id)).await {
+            Ok(result) => result,
+            Err(e) => {
+                // 档案已被删除等情况下不再计时
+                println!("⚠️ Dropping watch session {}: {}", session_id, e);
+                state.screen_time.lock().unwrap_or_else(|e| e.into_inner()).sessions.remove(&session_id);
+                continue;
+            }
+        };
+
+        // 离开允许观看的时间段时立即结束观看
+        match schedule_status(state, &profile).await {
+            Ok(schedule) if !schedule.allowed => {
+                if let Err(e) = finish_watch_session(state, session_id).await {
+                    println!("⚠️ Failed to end watch session {}: {}", session_id, e);
+                }
+                println!("🌙 Viewing window closed for profile '{}'", profile.name);
+                if let Err(e) = app.emit(SCHEDULE_LOCKOUT_EVENT, &schedule) {
+                    println!("⚠️ Failed to emit {}: {}", SCHEDULE_LOCKOUT_EVENT, e);
+                }
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => println!("⚠️ Failed to check viewing schedule: {}", e),
+        }
+
+        let mut status = match screen_time_status(state, &profile, &settings).await {
+            Ok(status) => status,
+            Err(e) => {
+                println!("⚠️ Failed to load screen time: {}", e);
+                continue;
+            }
+        };
+        status.session_id = Some(session_id);
+
+        let Some(remaining) = status.remaining_seconds else {
+            continue;
+        };
+        let event = if remaining == 0 {
+            if let Err(e) = finish_watch_session(state, session_id).await {
+                println!("⚠️ Failed to end watch session {}: {}", session_id, e);
+            }
+            println!("⏰ Screen time used up for profile {}", profile_id);
+            Some(SCREEN_TIME_LOCKOUT_EVENT)
+        } else if remaining <= settings.countdown_seconds as i64 {
+            Some(SCREEN_TIME_COUNTDOWN_EVENT)
+        } else if remaining <= settings.screen_time_warning_minutes as i64 * 60 {
+            // 提醒每个会话只发送一次
+            let mut tracker = state.screen_time.lock().unwrap_or_else(|e| e.into_inner());
+            match tracker.sessions.get_mut(&session_id) {
+                Some(session) if !session.warned => {
+                    session.warned = true;
+                    Some(SCREEN_TIME_WARNING_EVENT)
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        if let Some(event) = event {
+            if let Err(e) = app.emit(event, &status) {
+                println!("⚠️ Failed to emit {}: {}", event, e);
+            }
+        }
+    }
+}
+
+// 开始播放：同一档案只保留一个计时会话，重复调用会结束之前的会话
+#[command]
+async fn start_watch_session(
+    video_id: Option<String>,
+    profile_id: Option<i64>,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ScreenTimeStatus, AppError> {
+    let (profile, settings) = resolve_viewing_profile(&state, profile_id, parent_token.as_deref()).await?;
+    println!("▶️ Production: Starting watch session for profile '{}'", profile.name);
+
+    ensure_screen_time_available(&state, &profile, &settings).await?;
+    ensure_within_schedule(&state, &profile).await?;
+
+    let previous: Vec<i64> = state
+        .screen_time
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .sessions
+        .iter()
+        .filter(|(_, session)| session.profile_id == profile.id)
+        .map(|(session_id, _)| *session_id)
+        .collect();
+    for session_id in previous {
+        finish_watch_session(&state, session_id)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to end watch session: {}", e)))?;
+    }
+
+    let now = state.clock.now().to_rfc3339();
+    let session_id = sqlx::query(
+        "INSERT INTO watch_sessions (profile_id, video_id, started_at, last_heartbeat_at) VALUES (?, ?, ?, ?)"
+    )
+    .bind(profile.id)
+    .bind(&video_id)
+    .bind(&now)
+    .bind(&now)
+    .execute(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to start watch session: {}", e)))?
+    .last_insert_rowid();
+
+    state.screen_time.lock().unwrap_or_else(|e| e.into_inner()).sessions.insert(
+        session_id,
+        ActiveWatchSession {
+            profile_id: profile.id,
+            last_tick: std::time::Instant::now(),
+            warned: false,
+        },
+    );
+
+    let mut status = screen_time_status(&state, &profile, &settings).await?;
+    status.session_id = Some(session_id);
+    Ok(status)
+}
+
+// 结束播放：只能结束当前档案的会话，结束其他档案的会话需要家长解锁
+#[command]
+async fn stop_watch_session(
+    session_id: i64,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ScreenTimeStatus, AppError> {
+    println!("⏹️ Production: Stopping watch session {}", session_id);
+
+    let (profile_id,): (i64,) = sqlx::query_as("SELECT profile_id FROM watch_sessions WHERE id = ?")
+        .bind(session_id)
+        .fetch_optional(&*state.db)
+        .await?
+        .ok_or_else(|| AppError::InvalidInput(format!("Watch session {} does not exist", session_id)))?;
+    let active = resolve_profile(&state.db, None).await?;
+    if profile_id != active.id {
+        require_parent(&state, parent_token.as_deref())?;
+    }
+
+    finish_watch_session(&state, session_id)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to end watch session: {}", e)))?;
+
+    let (profile, settings) = profile_settings(&state, Some(profile_id)).await?;
+    screen_time_status(&state, &profile, &settings).await
+}
+
+#[command]
+async fn get_screen_time_status(
+    profile_id: Option<i64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ScreenTimeStatus, AppError> {
+    let (profile, settings) = profile_settings(&state, profile_id).await?;
+    let mut status = screen_time_status(&state, &profile, &settings).await?;
+    status.session_id = state
+        .screen_time
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .sessions
+        .iter()
+        .find(|(_, session)| session.profile_id == profile.id)
+        .map(|(session_id, _)| *session_id);
+    Ok(status)
+}
+
+// 观看记录：前端在播放过程中和结束时上报，作为报告和推荐的数据基础
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WatchEvent {
+    pub video_id: String,
+    pub profile_id: Option<i64>, // 为空时使用当前激活的档案
+    pub started_at: Option<String>, // 本次观看的开始时间（RFC3339），同一次观看多次上报时保持不变
+    pub watched_seconds: i64,
+    pub completed: bool,
+    pub video: Option<Video>, // 视频尚未保存时可一并提交元数据
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WatchHistoryEntry {
+    pub id: i64,
+    pub profile_id: i64,
+    pub video_id: String,
+    pub started_at: String,
+    pub watched_seconds: i64,
+    pub completed: bool,
+    pub video: Option<Video>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyWatchTotal {
+    pub date: String, // 本地日期
+    pub watched_seconds: i64,
+    pub videos: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChannelWatchTotal {
+    pub channel_title: String,
+    pub watched_seconds: i64,
+    pub videos: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WatchSummary {
+    pub profile_id: i64,
+    pub since: String,
+    pub total_seconds: i64,
+    pub videos_watched: i64, // 不同视频的数量
+    pub completed_count: i64,
+    pub daily: Vec<DailyWatchTotal>,
+    pub top_channels: Vec<ChannelWatchTotal>,
+}
+
+#[command]
+async fn record_watch_event(
+    event: WatchEvent,
+    state: tauri::State<'_, AppState>,
+) -> Result<WatchHistoryEntry, AppError> {
+    let profile = resolve_profile(&state.db, event.profile_id).await?;
+    println!("📺 Production: Recording watch of {} for profile '{}' ({}s)", event.video_id, profile.name, event.watched_seconds);
+
+    if event.watched_seconds < 0 {
+        return Err(AppError::InvalidInput("Watched seconds must not be negative".to_string()));
+    }
+    let now = chrono::Utc::now().to_rfc3339();
+    let started_at = match event.started_at.as_deref() {
+        Some(started_at) => chrono::DateTime::parse_from_rfc3339(started_at)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid started_at '{}': {}", started_at, e)))?
+            .with_timezone(&chrono::Utc)
+            .to_rfc3339(),
+        None => now.clone(),
+    };
+
+    let mut tx = state.db.begin().await
+        .map_err(|e| AppError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+    match event.video.as_ref().filter(|video| video.id == event.video_id) {
+        Some(video) => insert_video_if_missing(&mut tx, video).await?,
+        None => {
+            sqlx::query("INSERT OR IGNORE INTO videos (id, title, platform, updated_at) VALUES (?, ?, ?, ?)")
+                .bind(&event.video_id)
+                .bind("Unknown Video")
+                .bind(Platform::YouTube.id())
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    // 进度只增不减，完成状态一旦记录不会被撤销
+    let (id, watched_seconds, completed): (i64, i64, bool) = sqlx::query_as(
+        "INSERT INTO watch_history (profile_id, video_id, started_at, watched_seconds, completed, updated_at) 
+         VALUES (?, ?, ?, ?, ?, ?) 
+         ON CONFLICT(profile_id, video_id, started_at) DO UPDATE SET 
+            watched_seconds = MAX(watched_seconds, excluded.watched_seconds), 
+            completed = MAX(completed, excluded.completed), 
+            updated_at = excluded.updated_at 
+         RETURNING id, watched_seconds, completed"
+    )
+    .bind(profile.id)
+    .bind(&event.video_id)
+    .bind(&started_at)
+    .bind(event.watched_seconds)
+    .bind(event.completed)
+    .bind(&now)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to record watch event: {}", e)))?;
+
+    tx.commit().await
+        .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+
+    Ok(WatchHistoryEntry {
+        id,
+        profile_id: profile.id,
+        video_id: event.video_id,
+        started_at,
+        watched_seconds,
+        completed,
+        video: event.video,
+    })
+}
+
+// 单次最多返回的观看记录数
+const MAX_WATCH_HISTORY_LIMIT: i32 = 500;
+
+#[command]
+async fn get_watch_history(
+    profile_id: Option<i64>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<WatchHistoryEntry>, AppError> {
+    let profile = resolve_profile(&state.db, profile_id).await?;
+    println!("📺 Production: Loading watch history for profile '{}'", profile.name);
+
+    let rows = sqlx::query(&format!(
+        "SELECT w.id AS history_id, w.profile_id, w.video_id, w.started_at, w.watched_seconds, w.completed, {} 
+         FROM watch_history w 
+         LEFT JOIN videos v ON v.id = w.video_id 
+         LEFT JOIN profile_video_scores s ON s.video_id = w.video_id AND s.profile_id = w.profile_id 
+         WHERE w.profile_id = ? 
+         ORDER BY w.started_at DESC 
+         LIMIT ? OFFSET ?",
+        VIDEO_COLUMNS
+    ))
+    .bind(profile.id)
+    .bind(limit.unwrap_or(50).clamp(1, MAX_WATCH_HISTORY_LIMIT))
+    .bind(offset.unwrap_or(0).max(0))
+    .fetch_all(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to load watch history: {}", e)))?;
+
+    let mut history = Vec::new();
+    for row in rows {
+        history.push(WatchHistoryEntry {
+            id: row.try_get("history_id")?,
+            profile_id: row.try_get("profile_id")?,
+            video_id: row.try_get("video_id")?,
+            started_at: row.try_get("started_at")?,
+            watched_seconds: row.try_get("watched_seconds")?,
+            completed: row.try_get("completed")?,
+            video: video_from_row(&row).ok(),
+        });
+    }
+
+    Ok(history)
+}
+
+// 汇总最近若干天的观看情况：总时长、每日时长和观看最多的频道
+#[command]
+async fn get_watch_summary(
+    profile_id: Option<i64>,
+    days: Option<i32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<WatchSummary, AppError> {
+    let profile = resolve_profile(&state.db, profile_id).await?;
+    let days = days.unwrap_or(7).clamp(1, 366);
+    println!("📊 Production: Summarizing {} days of watch history for profile '{}'", days, profile.name);
+
+    let since = (chrono::Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339();
+
+    let (total_seconds, videos_watched, completed_count): (i64, i64, i64) = sqlx::query_as(
+        "SELECT COALESCE(SUM(watched_seconds), 0), COUNT(DISTINCT video_id), COALESCE(SUM(completed), 0) 
+         FROM watch_history 
+         WHERE profile_id = ? AND started_at >= ?"
+    )
+    .bind(profile.id)
+    .bind(&since)
+    .fetch_one(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to summarize watch history: {}", e)))?;
+
+    let daily = sqlx::query_as::<_, (String, i64, i64)>(
+        "SELECT date(started_at, 'localtime') AS day, SUM(watched_seconds), COUNT(DISTINCT video_id) 
+         FROM watch_history 
+         WHERE profile_id = ? AND started_at >= ? 
+         GROUP BY day 
+         ORDER BY day"
+    )
+    .bind(profile.id)
+    .bind(&since)
+    .fetch_all(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to summarize watch history: {}", e)))?
+    .into_iter()
+    .map(|(date, watched_seconds, videos)| DailyWatchTotal { date, watched_seconds, videos })
+    .collect();
+
+    let top_channels = sqlx::query_as::<_, (String, i64, i64)>(
+        "SELECT COALESCE(v.channel_title, 'Unknown'), SUM(w.watched_seconds), COUNT(DISTINCT w.video_id) 
+         FROM watch_history w 
+         LEFT JOIN videos v ON v.id = w.video_id 
+         WHERE w.profile_id = ? AND w.started_at >= ? 
+         GROUP BY 1 
+         ORDER BY 2 DESC 
+         LIMIT 10"
+    )
+    .bind(profile.id)
+    .bind(&since)
+    .fetch_all(&*state.db)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to summarize watch history: {}", e)))?
+    .into_iter()
+    .map(|(channel_title, watched_seconds, videos)| ChannelWatchTotal { channel_title, watched_seconds, videos })
+    .collect();
+
+    Ok(WatchSummary {
+        profile_id: profile.id,
+        since,
+        total_seconds,
+        videos_watched,
+        completed_count,
+        daily,
+        top_channels,
+    })
+}
+
+// 观看时间表：每个档案可以设置允许观看的时间段，时间段之外拒绝搜索和播放
+// 没有设置时间段表示不限制；家长可以临时延长
+const SCHEDULE_LOCKOUT_EVENT: &str = "viewing-schedule-lockout";
+const MAX_SCHEDULE_EXTENSION_MINUTES: i64 = 4 * 60;
+
+// 时间来源，时间表判断通过它获取当前时间，可以替换为固定时钟
+trait Clock: Send + Sync {
+    fn now(&self) -> chrono::DateTime<chrono::Utc>;
+}
+
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc::now()
+    }
+}
+
+// 允许观看的时间段：星期几（1=周一 … 7=周日）的 [start, end)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ViewingWindow {
+    pub weekdays: Vec<u32>,
+    pub start: String, // "HH:MM"
+    pub end: String, // "HH:MM"，全天结束可写 "24:00"
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ViewingSchedule {
+    pub timezone: Option<String>, // IANA 时区名，如 "Asia/Shanghai"；为空时使用系统时区
+    pub windows: Vec<ViewingWindow>, // 为空表示不限制观看时间
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduleStatus {
+    pub profile_id: i64,
+    pub schedule: ViewingSchedule,
+    pub in_window: bool, // 当前是否处于允许观看的时间段
+    pub grace_until: Option<String>, // 家长延长的截止时间（UTC）
+    pub allowed: bool,
+}
+
+// "HH:MM" 转为当天的分钟数
+fn parse_clock_minutes(value: &str) -> Result<u32> {
+    let (hours, minutes) = value
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid time '{}', expected HH:MM", value))?;
+    let hours: u32 = hours.parse().map_err(|_| anyhow::anyhow!("Invalid time '{}'", value))?;
+    let minutes: u32 = minutes.parse().map_err(|_| anyhow::anyhow!("Invalid time '{}'", value))?;
+    if minutes >= 60 || hours > 24 || (hours == 24 && minutes > 0) {
+        return Err(anyhow::anyhow!("Invalid time '{}'", value));
+    }
+    Ok(hours * 60 + minutes)
+}
+
+impl ViewingWindow {
+    fn validate(&self) -> Result<()> {
+        if self.weekdays.is_empty() || self.weekdays.iter().any(|day| !(1..=7).contains(day)) {
+            return Err(anyhow::anyhow!("Weekdays must be between 1 (Monday) and 7 (Sunday)"));
+        }
+        if parse_clock_minutes(&self.start)? >= parse_clock_minutes(&self.end)? {
+            return Err(anyhow::anyhow!(
+                "Viewing window {}-{} must end after it starts",
+                self.start,
+                self.end
+            ));
+        }
+        Ok(())
+    }
+
+    fn contains(&self, weekday: u32, minute: u32) -> bool {
+        match (parse_clock_minutes(&self.start), parse_clock_minutes(&self.end)) {
+            (Ok(start), Ok(end)) => self.weekdays.contains(&weekday) && start <= minute && minute < end,
+            _ => false,
+        }
+    }
+}
+
+impl ViewingSchedule {
+    fn validate(&self) -> Result<()> {
+        self.tz()?;
+        self.windows.iter().try_for_each(ViewingWindow::validate)
+    }
+
+    // 时间表所用的时区，None 表示使用系统时区
+    fn tz(&self) -> Result<Option<chrono_tz::Tz>> {
+        self.timezone
+            .as_deref()
+            .map(str::trim)
+            .filter(|tz| !tz.is_empty())
+            .map(|timezone| {
+                timezone
+                    .parse::<chrono_tz::Tz>()
+                    .map_err(|e| anyhow::anyhow!("Unknown timezone '{}': {}", timezone, e))
+            })
+            .transpose()
+    }
+
+    // 给定时刻在时间表所用时区中的星期和当天分钟数
+    fn local_weekday_minute(&self, now: chrono::DateTime<chrono::Utc>) -> Result<(u32, u32)> {
+        use chrono::{Datelike, Timelike};
+
+        let (weekday, hour, minute) = match self.tz()? {
+            Some(tz) => {
+                let local = now.with_timezone(&tz);
+                (local.weekday(), local.hour(), local.minute())
+            }
+            None => {
+                let local = now.with_timezone(&chrono::Local);
+                (local.weekday(), local.hour(), local.minute())
+            }
+        };
+        Ok((weekday.number_from_monday(), hour * 60 + minute))
+    }
+
+    // 给定时刻在时间表所用时区中的日期
+    fn local_date(&self, now: chrono::DateTime<chrono::Utc>) -> Result<String> {
+        let date = match self.tz()? {
+            Some(tz) => now.with_timezone(&tz).date_naive(),
+            None => now.with_timezone(&chrono::Local).date_naive(),
+        };
+        Ok(date.format("%Y-%m-%d").to_string())
+    }
+
+    fn allows(&self, now: chrono::DateTime<chrono::Utc>) -> Result<bool> {
+        if self.windows.is_empty() {
+            return Ok(true);
+        }
+        let (weekday, minute) = self.local_weekday_minute(now)?;
+        Ok(self.windows.iter().any(|window| window.contains(weekday, minute)))
+    }
+}
+
+async fn load_viewing_schedule(pool: &SqlitePool, profile_id: i64) -> Result<(ViewingSchedule, Option<String>), AppError> {
+    let (timezone, grace_until) = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        "SELECT timezone, schedule_grace_until FROM profiles WHERE id = ?"
+    )
+    .bind(profile_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to load viewing schedule: {}", e)))?
+    .ok_or_else(|| AppError::InvalidInput(format!("Profile {} does not exist", profile_id)))?;
+
+    let rows = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT weekdays, start_time, end_time FROM viewing_windows WHERE profile_id = ? ORDER BY position"
+    )
+    .bind(profile_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Database(format!("Failed to load viewing schedule: {}", e)))?;
+
+    let windows = rows
+        .into_iter()
+        .map(|(weekdays, start, end)| ViewingWindow {
+            weekdays: weekdays.split(',').filter_map(|day| day.trim().parse().ok()).collect(),
+            start,
+            end,
+        })
+        .collect();
+
+    Ok((ViewingSchedule { timezone, windows }, grace_until))
+}
+
+async fn schedule_status(state: &AppState, profile: &ChildProfile) -> Result<ScheduleStatus, AppError> {
+    let (schedule, grace_until) = load_viewing_schedule(&state.db, profile.id).await?;
+    evaluate_schedule(profile.id, schedule, grace_until, state.clock.as_ref())
+}
+
+// 按时钟的当前时间判断时间表和家长延长是否允许观看
+fn evaluate_schedule(
+    profile_id: i64,
+    schedule: ViewingSchedule,
+    grace_until: Option<String>,
+    clock: &dyn Clock,
+) -> Result<ScheduleStatus, AppError> {
+    let now = clock.now();
+
+    let in_window = schedule
+        .allows(now)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid viewing schedule: {}", e)))?;
+    // 过期的延长不再返回
+    let grace_until = grace_until.filter(|until| {
+        chrono::DateTime::parse_from_rfc3339(until).is_ok_and(|until| until > now)
+    });
+
+    Ok(ScheduleStatus {
+        profile_id,
+        allowed: in_window || grace_until.is_some(),
+        schedule,
+        in_window,
+        grace_until,
+    })
+}
+
+async fn ensure_within_schedule(state: &AppState, profile: &ChildProfile) -> Result<(), AppError> {
+    if schedule_status(state, profile).await?.allowed {
+        Ok(())
+    } else {
+        Err(AppError::OutsideSchedule(format!(
+            "'{}' is outside the allowed viewing time",
+            profile.name
+        )))
+    }
+}
+
+#[command]
+async fn get_viewing_schedule(
+    profile_id: Option<i64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ScheduleStatus, AppError> {
+    let profile = resolve_profile(&state.db, profile_id).await?;
+    schedule_status(&state, &profile).await
+}
+
+#[command]
+async fn set_viewing_schedule(
+    profile_id: Option<i64>,
+    schedule: ViewingSchedule,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ScheduleStatus, AppError> {
+    require_parent(&state, parent_token.as_deref())?;
+
+    let profile = resolve_profile(&state.db, profile_id).await?;
+    println!("🗓️ Production: Saving viewing schedule for profile '{}'", profile.name);
+
+    schedule
+        .validate()
+        .map_err(|e| AppError::InvalidInput(format!("Invalid viewing schedule: {}", e)))?;
+
+    let mut tx = state.db.begin().await
+        .map_err(|e| AppError::Database(format!("Failed to begin transaction: {}", e)))?;
+    sqlx::query("UPDATE profiles SET timezone = ?, updated_at = ? WHERE id = ?")
+        .bind(schedule.timezone.as_deref().map(str::trim).filter(|tz| !tz.is_empty()))
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(profile.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM viewing_windows WHERE profile_id = ?")
+        .bind(profile.id)
+        .execute(&mut *tx)
+        .await?;
+    for (position, window) in schedule.windows.iter().enumerate() {
+        let weekdays = window.weekdays.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+        sqlx::query(
+            "INSERT INTO viewing_windows (profile_id, weekdays, start_time, end_time, position) 
+             VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(profile.id)
+        .bind(weekdays)
+        .bind(window.start.trim())
+        .bind(window.end.trim())
+        .bind(position as i64)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+        .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+
+    schedule_status(&state, &profile).await
+}
+
+// 家长在时间段之外临时允许观看若干分钟
+#[command]
+async fn grant_schedule_extension(
+    profile_id: Option<i64>,
+    minutes: i64,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ScheduleStatus, AppError> {
+    require_parent(&state, parent_token.as_deref())?;
+
+    let profile = resolve_profile(&state.db, profile_id).await?;
+    println!("🗓️ Production: Extending viewing time for profile '{}' by {} minutes", profile.name, minutes);
+
+    if !(1..=MAX_SCHEDULE_EXTENSION_MINUTES).contains(&minutes) {
+        return Err(AppError::InvalidInput(format!(
+            "Extension must be between 1 and {} minutes",
+            MAX_SCHEDULE_EXTENSION_MINUTES
+        )));
+    }
+
+    let grace_until = state.clock.now() + chrono::Duration::minutes(minutes);
+    sqlx::query("UPDATE profiles SET schedule_grace_until = ? WHERE id = ?")
+        .bind(grace_until.to_rfc3339())
+        .bind(profile.id)
+        .execute(&*state.db)
+        .await
+        .map_err(|e| AppError::Database(format!("Failed to extend viewing time: {}", e)))?;
+
+    schedule_status(&state, &profile).await
+}
+
+#[command]
+async fn get_parent_pin_status(
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ParentPinStatus, AppError> {
+    let pin_set = load_parent_pin(&state)
+        .map_err(|e| AppError::Internal(format!("Failed to read parent PIN: {}", e)))?
+        .is_some();
+    let lock = state.parent_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+    Ok(ParentPinStatus {
+        pin_set,
+        unlocked: !pin_set || lock.session_valid(parent_token.as_deref()),
+        lockout_seconds: lock.lockout_remaining(),
+    })
+}
+
+// 设置或修改家长PIN；已有PIN时需要先解锁
+#[command]
+async fn set_parent_pin(
+    pin: String,
+    parent_token: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, AppError> {
+    println!("🔐 Production: Setting parent PIN");
+
+    require_parent(&state, parent_token.as_deref())?;
+
+    let pin = pin.trim();
+    if pin.chars().count() < 4 || pin.chars().count() > 64 {
+        return Err(AppError::InvalidInput("Parent PIN must be 4-64 characters".to_string()));
+    }
+
+    // Argon2 哈希比较耗时，放到阻塞线程中执行
+    let pin = pin.to_string();
+    let hash = tauri::async_runtime::spawn_blocking(move || hash_parent_pin(&pin))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to hash parent PIN: {}", e)))?
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let stored = StoredParentPin {
+        hash,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let content = serde_json::to_string_pretty(&stored)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize parent PIN: {}", e)))?;
+    tokio::fs::write(state.parent_pin_path(), content)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to save parent PIN: {}", e)))?;
+
+    // 修改PIN后旧的解锁令牌全部失效
+    state.parent_lock.lock().unwrap_or_else(|e| e.into_inner()).session = None;
+
+    Ok("Parent PIN saved successfully".to_string())
+}
+
+#[command]
+async fn unlock_parent(
+    pin: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ParentUnlock, AppError> {
+    println!("🔐 Production: Parent unlock requested");
+
+    let stored = load_parent_pin(&state)
+        .map_err(|e| AppError::Internal(format!("Failed to read parent PIN: {}", e)))?
+        .ok_or_else(|| AppError::InvalidInput("Parent PIN has not been set".to_string()))?;
+
+    let lockout_seconds = state.parent_lock.lock().unwrap_or_else(|e| e.into_inner()).lockout_remaining();
+    if lockout_seconds > 0 {
+        return Err(AppError::ParentLocked(format!(
+            "Too many incorrect attempts, try again in {} seconds",
+            lockout_seconds
+        )));
+    }
+
+    // Argon2 验证比较耗时，放到阻塞线程中执行
+    let pin_matches = tauri::async_runtime::spawn_blocking(move || verify_parent_pin(&stored, pin.trim()))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to verify parent PIN: {}", e)))?;
+
+    let mut lock = state.parent_lock.lock().unwrap_or_else(|e| e.into_inner());
+    if !pin_matches {
+        let lockout_secs = lock.record_failure();
+        if lockout_secs > 0 {
+            println!("⚠️ Parent PIN locked for {}s after {} consecutive failures", lockout_secs, lock.failed_attempts);
+        }
+        return Err(AppError::ParentLocked("Incorrect parent PIN".to_string()));
+    }
+
+    let token = lock.record_success();
+
+    Ok(ParentUnlock {
+        token,
+        expires_in_seconds: PARENT_SESSION_TTL_SECS,
+    })
+}
+
 #[command]
-async fn get_search_history(
-    limit: Option<i32>,
-    state: tauri::State<'_, AppState>,
-) -> Result<Vec<SearchHistoryEntry>, String> {
-    println!("📚 Production: Loading search history");
-    
-    let limit_value = limit.unwrap_or(20);
-    
-    let rows = sqlx::query_as::<_, (i64, String, String, String, i32, String)>(
-        "SELECT id, query, platform, filter_mode, results_count, created_at 
-         FROM search_history 
-         ORDER BY created_at DESC 
-         LIMIT ?"
-    )
-    .bind(limit_value)
-    .fetch_all(&*state.db)
-    .await
-    .map_err(|e| format!("Failed to load search history: {}", e))?;
-    
-    let history = rows.into_iter().map(|(id, query, platform, filter_mode, results_count, created_at)| {
-        SearchHistoryEntry {
-            id,
-            query,
-            platform,
-            filter_mode,
-            results_count,
-            created_at,
-        }
-    }).collect();
-    
-    Ok(history)
+async fn lock_parent(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    println!("🔒 Production: Parent session locked");
+    state.parent_lock.lock().unwrap_or_else(|e| e.into_inner()).session = None;
+    Ok("Parent session locked".to_string())
 }
 
 #[command]
-async fn get_settings(state: tauri::State<'_, AppState>) -> Result<AppSettings, String> {
+async fn get_settings(state: tauri::State<'_, AppState>) -> Result<AppSettings, AppError> {
     println!("⚙️ Production: Loading settings");
     
     // 从文件加载设置
-    let loaded_settings = load_settings_from_file(&*state).await;
+    let loaded_settings = load_settings_from_file(&state).await;
     
     // 更新内存中的设置
     {
@@ -2082,9 +6450,16 @@ async fn get_settings(state: tauri::State<'_, AppState>) -> Result<AppSettings,
 #[command]
 async fn save_settings(
     settings: AppSettings,
+    parent_token: Option<String>,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     println!("⚙️ Production: Saving settings");
+
+    require_parent(&state, parent_token.as_deref())?;
+    
+    settings
+        .validate()
+        .map_err(|e| AppError::InvalidInput(format!("Invalid settings: {}", e)))?;
     
     // 保存到内存
     {
@@ -2093,9 +6468,9 @@ async fn save_settings(
     }
     
     // 保存到文件
-    save_settings_to_file(&*state, &settings)
+    save_settings_to_file(&state, &settings)
         .await
-        .map_err(|e| format!("Failed to save settings: {}", e))?;
+        .map_err(|e| AppError::Internal(format!("Failed to save settings: {}", e)))?;
     
     Ok("Settings saved successfully".to_string())
 }
@@ -2103,12 +6478,30 @@ async fn save_settings(
 // 这个函数已被 get_search_history 替代，保留仅为兼容性
 // 建议使用新的 get_search_history 函数
 
+// 连接测试结果，失败时附带错误代码
+fn connection_test_result(result: Result<String>) -> serde_json::Value {
+    match result {
+        Ok(message) => serde_json::json!({
+            "success": true,
+            "message": message
+        }),
+        Err(e) => {
+            let error = AppError::classify(e, AppError::Network);
+            serde_json::json!({
+                "success": false,
+                "code": error.code(),
+                "error": error.message()
+            })
+        }
+    }
+}
+
 // TEST API CONNECTIONS
 #[command]
 async fn test_api_connections(
     api_keys: HashMap<String, String>,
     state: tauri::State<'_, AppState>,
-) -> Result<HashMap<String, serde_json::Value>, String> {
+) -> Result<HashMap<String, serde_json::Value>, AppError> {
     println!("🧪 Production: Testing API connections");
     
     let mut results = HashMap::new();
@@ -2116,48 +6509,47 @@ async fn test_api_connections(
     // 测试YouTube API
     if let Some(youtube_key) = api_keys.get("youtube_api_key") {
         if !youtube_key.is_empty() {
-            match test_youtube_api(&state.client, youtube_key).await {
-                Ok(message) => {
-                    results.insert("youtube".to_string(), serde_json::json!({
-                        "success": true,
-                        "message": message
-                    }));
-                }
-                Err(e) => {
-                    results.insert("youtube".to_string(), serde_json::json!({
-                        "success": false,
-                        "error": e.to_string()
-                    }));
-                }
-            }
+            let units_used = std::sync::atomic::AtomicI64::new(0);
+            let api = YouTubeApi {
+                client: &state.client,
+                api_key: youtube_key,
+                db: &state.db,
+                clock: state.clock.as_ref(),
+                units_used: &units_used,
+            };
+            let daily_quota = state.settings.lock().await.youtube_daily_quota as i64;
+            let result = test_youtube_api(api, daily_quota).await;
+            results.insert("youtube".to_string(), connection_test_result(result));
         } else {
             results.insert("youtube".to_string(), serde_json::json!({
                 "success": false,
+                "code": "MissingApiKey",
                 "error": "YouTube API key not provided"
             }));
         }
     }
     
-    // 测试OpenAI API
-    if let Some(openai_key) = api_keys.get("openai_api_key") {
-        if !openai_key.is_empty() {
-            match test_openai_api(&state.client, openai_key).await {
-                Ok(message) => {
-                    results.insert("openai".to_string(), serde_json::json!({
-                        "success": true,
-                        "message": message
-                    }));
-                }
-                Err(e) => {
-                    results.insert("openai".to_string(), serde_json::json!({
-                        "success": false,
-                        "error": e.to_string()
-                    }));
-                }
-            }
+    // 测试OpenAI API（包括自定义的OpenAI兼容接口）
+    if api_keys.contains_key("openai_api_key") || api_keys.contains_key("openai_base_url") {
+        let provider = state.providers.resolve("openai");
+        let mut config = provider.config(&*state.settings.lock().await);
+        if let Some(openai_key) = api_keys.get("openai_api_key") {
+            config.api_key = openai_key.clone();
+        }
+        if let Some(base_url) = api_keys.get("openai_base_url") {
+            config.base_url = base_url.clone();
+        }
+        if let Some(model) = api_keys.get("openai_model") {
+            config.model = model.clone();
+        }
+        
+        if provider.is_configured(&config) {
+            let result = test_openai_api(&state.client, &config).await;
+            results.insert("openai".to_string(), connection_test_result(result));
         } else {
             results.insert("openai".to_string(), serde_json::json!({
                 "success": false,
+                "code": "MissingApiKey",
                 "error": "OpenAI API key not provided"
             }));
         }
@@ -2166,23 +6558,19 @@ async fn test_api_connections(
     // 测试Anthropic API
     if let Some(anthropic_key) = api_keys.get("anthropic_api_key") {
         if !anthropic_key.is_empty() {
-            match test_anthropic_api(&state.client, anthropic_key).await {
-                Ok(message) => {
-                    results.insert("anthropic".to_string(), serde_json::json!({
-                        "success": true,
-                        "message": message
-                    }));
-                }
-                Err(e) => {
-                    results.insert("anthropic".to_string(), serde_json::json!({
-                        "success": false,
-                        "error": e.to_string()
-                    }));
-                }
+            let provider = state.providers.resolve("anthropic");
+            let mut config = provider.config(&*state.settings.lock().await);
+            config.api_key = anthropic_key.clone();
+            if let Some(model) = api_keys.get("anthropic_model") {
+                config.model = model.clone();
             }
+            
+            let result = test_anthropic_api(&state.client, &config).await;
+            results.insert("anthropic".to_string(), connection_test_result(result));
         } else {
             results.insert("anthropic".to_string(), serde_json::json!({
                 "success": false,
+                "code": "MissingApiKey",
                 "error": "Anthropic API key not provided"
             }));
         }
@@ -2191,46 +6579,41 @@ async fn test_api_connections(
     Ok(results)
 }
 
-// 辅助函数
-fn create_fallback_response(query: &str) -> SearchResponse {
-    let fallback_video = Video {
-        id: format!("fallback_{}", chrono::Utc::now().timestamp()),
-        title: format!("配置API密钥以搜索: {}", query),
-        description: Some("请在设置页面配置YouTube API密钥以启用真实搜索功能".to_string()),
-        channel_title: Some("系统提示".to_string()),
-        duration: Some(0),
-        view_count: Some(0),
-        like_count: Some(0),
-        published_at: Some(chrono::Utc::now().to_rfc3339()),
-        thumbnail_url: Some("https://via.placeholder.com/320x180/f5f5f5/666666?text=API+KEY+REQUIRED".to_string()),
-        cached_at: Some(chrono::Utc::now().to_rfc3339()),
-        ai_score: None,
-        education_score: None,
-        safety_score: None,
-        age_appropriate: None,
-        tags: None,
-        subtitles: None,
-    };
-
-    SearchResponse {
-        videos: vec![fallback_video],
-        total_found: 1,
-        search_time: 0.0,
-        ai_analysis_time: 0.0,
+fn filter_videos_by_mode(mut videos: Vec<Video>, filter_mode: &str, unverified_policy: &str) -> Vec<Video> {
+    // 未知模式不放行任何视频
+    if !matches!(filter_mode, "strict" | "educational" | "balanced") {
+        println!("⚠️ Unknown filter mode '{}', rejecting all videos", filter_mode);
+        return Vec::new();
     }
-}
 
-fn filter_videos_by_mode(mut videos: Vec<Video>, filter_mode: &str) -> Vec<Video> {
-    // 只保留有AI分析结果的视频进行筛选
     videos.retain(|video| {
+        // 未经AI验证的视频由策略决定，缺失评分不能视为通过
+        if !video.is_verified() {
+            return matches!(unverified_policy, "warn" | "allow");
+        }
+
+        // 任何模式下都要求AI明确判定适龄
+        if video.age_appropriate != Some(true) {
+            return false;
+        }
+
         match filter_mode {
-            "strict" => video.safety_score.unwrap_or(0.0) >= 0.9 && video.age_appropriate.unwrap_or(false),
+            "strict" => video.safety_score.unwrap_or(0.0) >= 0.9,
             "educational" => video.education_score.unwrap_or(0.0) >= 0.75,
-            "balanced" => video.ai_score.unwrap_or(0.6) >= 0.6,
-            _ => true,
+            "balanced" => video.ai_score.unwrap_or(0.0) >= 0.6,
+            _ => false,
         }
     });
 
+    // "warn" 策略下放行的未验证视频带上提示，由前端标出；"allow" 则不提示
+    for video in videos.iter_mut() {
+        video.warning = if unverified_policy == "warn" && !video.is_verified() {
+            Some("该视频未经AI审核，请家长留意".to_string())
+        } else {
+            None
+        };
+    }
+
     // 按AI评分排序
     videos.sort_by(|a, b| {
         let score_a = match filter_mode {
@@ -2250,90 +6633,102 @@ fn filter_videos_by_mode(mut videos: Vec<Video>, filter_mode: &str) -> Vec<Video
 }
 
 // API测试函数 - 参考Chrome扩展的API测试
-async fn test_youtube_api(client: &Client, api_key: &str) -> Result<String> {
+async fn test_youtube_api(api: YouTubeApi<'_>, daily_quota: i64) -> Result<String> {
     println!("🧪 Testing YouTube Data API...");
     
     let test_url = format!(
         "https://www.googleapis.com/youtube/v3/videos?part=snippet&chart=mostPopular&maxResults=1&key={}",
-        api_key
+        api.api_key
     );
 
-    let response = client
+    let response = api.client
         .get(&test_url)
         .timeout(tokio::time::Duration::from_secs(10))
         .send()
         .await
-        .map_err(|e| anyhow::anyhow!("YouTube API test request failed: {}", e))?;
-
-    if response.status().is_success() {
-        let quota_info = response
-            .headers()
-            .get("x-ratelimit-remaining")
-            .and_then(|h| h.to_str().ok())
-            .map(|s| format!("Remaining quota: {}", s))
-            .unwrap_or_else(|| "Quota info not available".to_string());
-            
-        Ok(format!("YouTube API connection successful. {}", quota_info))
-    } else if response.status() == 403 {
-        Err(anyhow::anyhow!("YouTube API quota exceeded or invalid API key"))
-    } else if response.status() == 400 {
-        Err(anyhow::anyhow!("YouTube API request parameters error"))
-    } else {
-        Err(anyhow::anyhow!("YouTube API error: {}", response.status()))
-    }
+        .map_err(|e| AppError::Network(format!("YouTube API test request failed: {}", e)))?;
+    api.record_quota("videos.list", VIDEOS_LIST_QUOTA_COST).await;
+    check_youtube_response(response, "videos.list").await?;
+
+    // YouTube 不在响应中返回剩余配额，使用本地记录的消耗量
+    let quota_info = match quota_used_today(api.db, api.clock).await {
+        Ok(used) => format!("Quota used today: {}/{} units", used, daily_quota),
+        Err(_) => "Quota usage not available".to_string(),
+    };
+        
+    Ok(format!("YouTube API connection successful. {}", quota_info))
 }
 
-async fn test_openai_api(client: &Client, api_key: &str) -> Result<String> {
-    println!("🧪 Testing OpenAI API...");
+async fn test_openai_api(client: &Client, config: &ProviderConfig) -> Result<String> {
+    println!("🧪 Testing OpenAI API at {}...", config.base_url);
     
-    let response = client
-        .get("https://api.openai.com/v1/models")
-        .header("Authorization", format!("Bearer {}", api_key))
+    let response = openai_authorized(client.get(openai_endpoint(&config.base_url, "models")), &config.api_key)
         .timeout(tokio::time::Duration::from_secs(10))
         .send()
         .await
-        .map_err(|e| anyhow::anyhow!("OpenAI API test request failed: {}", e))?;
+        .map_err(|e| AppError::Network(format!("OpenAI API test request failed: {}", e)))?;
 
     if response.status().is_success() {
         let data: serde_json::Value = response.json().await?;
-        let model_count = data["data"].as_array().map(|a| a.len()).unwrap_or(0);
-        Ok(format!("OpenAI API connection successful. Available models: {}", model_count))
+        let models: Vec<&str> = data["data"]
+            .as_array()
+            .map(|models| models.iter().filter_map(|m| m["id"].as_str()).collect())
+            .unwrap_or_default();
+        
+        // 确认所选模型在 /models 列表中
+        if !models.contains(&config.model.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Model '{}' is not available on this endpoint ({} models listed)",
+                config.model,
+                models.len()
+            ));
+        }
+        
+        Ok(format!(
+            "OpenAI API connection successful. Model '{}' is available ({} models listed)",
+            config.model,
+            models.len()
+        ))
     } else {
-        Err(anyhow::anyhow!("OpenAI API error: {}", response.status()))
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        Err(provider_status_error("OpenAI", status, &body).into())
     }
 }
 
-async fn test_anthropic_api(client: &Client, api_key: &str) -> Result<String> {
-    println!("🧪 Testing Anthropic API...");
+async fn test_anthropic_api(client: &Client, config: &ProviderConfig) -> Result<String> {
+    println!("🧪 Testing Anthropic API with model {}...", config.model);
     
+    // 用所选模型发送最小请求，模型不存在时接口会返回错误
     let test_request = AnthropicRequest {
-        model: "claude-3-haiku-20240307".to_string(),
+        model: config.model.clone(),
         max_tokens: 10,
+        temperature: config.temperature,
         messages: vec![AnthropicMessage {
             role: "user".to_string(),
             content: "Hello".to_string(),
         }],
+        tools: None,
+        tool_choice: None,
     };
 
     let response = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("x-api-key", api_key)
+        .post(format!("{}/messages", config.base_url))
+        .header("x-api-key", &config.api_key)
         .header("anthropic-version", "2023-06-01")
         .header("Content-Type", "application/json")
         .json(&test_request)
         .timeout(tokio::time::Duration::from_secs(10))
         .send()
         .await
-        .map_err(|e| anyhow::anyhow!("Anthropic API test request failed: {}", e))?;
+        .map_err(|e| AppError::Network(format!("Anthropic API test request failed: {}", e)))?;
 
     if response.status().is_success() {
-        Ok("Anthropic API connection successful".to_string())
+        Ok(format!("Anthropic API connection successful. Model '{}' is available", config.model))
     } else {
-        let error_data: serde_json::Value = response.json().await.unwrap_or_default();
-        let error_msg = error_data["error"]["message"]
-            .as_str()
-            .unwrap_or("Unknown error");
-        Err(anyhow::anyhow!("Anthropic API error: {}", error_msg))
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        Err(provider_status_error("Anthropic", status, &body).into())
     }
 }
 
@@ -2346,7 +6741,11 @@ fn main() {
             let app_state = tauri::async_runtime::block_on(async {
                 AppState::new(app.handle()).await.expect("Failed to create app state")
             });
+            // 后台定期清理过期的缓存视频
+            tauri::async_runtime::spawn(run_cache_purge_task(app_state.db.clone()));
             app.manage(app_state);
+            // 后台累计观看时长并推送提醒、倒计时和锁定事件
+            tauri::async_runtime::spawn(run_screen_time_task(app.handle().clone()));
             
             #[cfg(debug_assertions)]
             {
@@ -2382,8 +6781,784 @@ fn main() {
             save_settings,
             clear_cache,
             get_search_history,
+            list_profiles,
+            get_active_profile,
+            create_profile,
+            update_profile,
+            delete_profile,
+            switch_profile,
+            get_parent_pin_status,
+            set_parent_pin,
+            unlock_parent,
+            lock_parent,
+            start_watch_session,
+            stop_watch_session,
+            get_screen_time_status,
+            get_viewing_schedule,
+            set_viewing_schedule,
+            grant_schedule_extension,
+            record_watch_event,
+            get_watch_history,
+            get_watch_summary,
+            get_quota_usage,
             test_api_connections
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_videos() -> Vec<Video> {
+        numbered_videos(3)
+    }
+
+    fn numbered_videos(count: usize) -> Vec<Video> {
+        (1..=count)
+            .map(|n| Video {
+                id: format!("v{}", n),
+                title: format!("Video v{}", n),
+                duration: Some(300),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn video_ids(videos: &[Video]) -> Vec<&str> {
+        videos.iter().map(|video| video.id.as_str()).collect()
+    }
+
+    fn batch_item(index: &str, score: &str) -> String {
+        format!(
+            r#"{{"index":{},"score":{},"suitable":true,"reason":"ok","educational_value":80,"safety_score":95,"age_fit":85,"production_quality":70,"recommended_age":"3-5岁"}}"#,
+            index, score
+        )
+    }
+
+    // 测试用的AI提供商：不发请求，按提示词中的标题逐条返回通过的评分
+    #[derive(Default)]
+    struct StubProvider {
+        failing_video: Option<&'static str>, // 批次中包含该视频时返回错误
+        calls: std::sync::Mutex<Vec<Vec<String>>>, // 每次请求涉及的视频id
+    }
+
+    impl AiProvider for StubProvider {
+        fn id(&self) -> &'static str {
+            "stub"
+        }
+
+        fn config(&self, _settings: &AppSettings) -> ProviderConfig {
+            ProviderConfig {
+                api_key: "stub-key".to_string(),
+                model: "stub-model".to_string(),
+                max_concurrency: 4,
+                ..Default::default()
+            }
+        }
+
+        fn complete<'a>(
+            &'a self,
+            _client: &'a Client,
+            _config: &'a ProviderConfig,
+            content: String,
+            _max_tokens: u32,
+        ) -> ProviderFuture<'a, String> {
+            Box::pin(async move {
+                let ids: Vec<String> = content
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("标题：Video "))
+                    .map(str::to_string)
+                    .collect();
+                self.calls.lock().unwrap().push(ids.clone());
+
+                // 单视频分析的提示词中没有批量格式的标题行
+                if ids.is_empty() {
+                    return Ok(r#"{"education_score":0.8,"safety_score":0.9,"age_appropriate":true,"overall_score":0.85,"recommended_age":"3-5岁","reasoning":"stub"}"#.to_string());
+                }
+                if self.failing_video.is_some_and(|failing| ids.iter().any(|id| id == failing)) {
+                    return Err(anyhow::anyhow!("stub provider failure"));
+                }
+
+                // 第一个块最晚返回，确认并发完成的先后不影响结果顺序
+                if ids[0] == "v1" {
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                }
+
+                let items: Vec<_> = (1..=ids.len()).map(|index| batch_item(&index.to_string(), "90")).collect();
+                Ok(format!(r#"{{"videos":[{}]}}"#, items.join(",")))
+            })
+        }
+    }
+
+    fn stub_options(context: &PromptContext) -> BatchAnalysisOptions<'_> {
+        BatchAnalysisOptions {
+            custom_prompt: None,
+            context,
+            min_duration: None,
+            max_duration: None,
+        }
+    }
+
+    // 每个测试使用独立的临时数据库并执行全部迁移
+    async fn migrated_pool(name: &str) -> SqlitePool {
+        let dir = std::env::temp_dir().join(format!("kid-videos-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("app.db");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(&format!("sqlite:{}?mode=rwc", db_path.display()))
+            .await
+            .unwrap();
+        run_migrations(&pool, &db_path).await.unwrap();
+        pool
+    }
+
+    #[test]
+    fn parse_batch_analysis_response_cases() {
+        let all = [batch_item("1", "90"), batch_item("2", "90"), batch_item("3", "90")].join(",");
+        // (名称, 模型输出, 期望的 invalid_items 序号, 期望通过验证的视频)
+        let cases = vec![
+            ("plain object", format!(r#"{{"videos":[{}]}}"#, all), vec![], vec!["v1", "v2", "v3"]),
+            ("fenced json", format!("```json\n{{\"videos\":[{}]}}\n```", all), vec![], vec!["v1", "v2", "v3"]),
+            ("leading prose", format!("Here are the results:\n[{}]", all), vec![], vec!["v1", "v2", "v3"]),
+            ("prose with bracket", format!(r#"results [below]: {{"videos":[{}]}}"#, all), vec![], vec!["v1", "v2", "v3"]),
+            (
+                "truncated array",
+                format!(r#"{{"videos":[{},{},{{"index":3,"score":"#, batch_item("1", "90"), batch_item("2", "90")),
+                vec![],
+                vec!["v1", "v2"],
+            ),
+            (
+                "numbers as strings",
+                format!("[{},{},{}]", batch_item("\"1\"", "\"90\""), batch_item("2", "\" 90 \""), batch_item("3", "90")),
+                vec![],
+                vec!["v1", "v2", "v3"],
+            ),
+            (
+                "out-of-range score",
+                format!("[{},{},{}]", batch_item("1", "90"), batch_item("2", "150"), batch_item("3", "-5")),
+                vec![Some(2), Some(3)],
+                vec!["v1"],
+            ),
+            (
+                "duplicate index",
+                format!("[{},{},{},{}]", batch_item("1", "90"), batch_item("1", "80"), batch_item("2", "90"), batch_item("3", "90")),
+                vec![Some(1)],
+                vec!["v1", "v2", "v3"],
+            ),
+            (
+                "out-of-range index",
+                format!("[{},{},{},{}]", batch_item("1", "90"), batch_item("7", "90"), batch_item("2", "90"), batch_item("3", "90")),
+                vec![None],
+                vec!["v1", "v2", "v3"],
+            ),
+            ("omitted videos", format!("[{}]", batch_item("1", "90")), vec![], vec!["v1"]),
+        ];
+
+        let videos = test_videos();
+        for (name, response, expected_invalid, expected_verified) in cases {
+            let outcome = parse_batch_analysis_response(&videos, &response, None, None)
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+
+            let invalid: Vec<_> = outcome.invalid_items.iter().map(|item| item.index).collect();
+            assert_eq!(invalid, expected_invalid, "{}: invalid_items", name);
+
+            let mut verified: Vec<_> = outcome
+                .videos
+                .iter()
+                .filter(|video| video.is_verified())
+                .map(|video| video.id.as_str())
+                .collect();
+            verified.sort();
+            assert_eq!(verified, expected_verified, "{}: verified videos", name);
+
+            // 每个输入视频恰好出现一次（通过或未验证）
+            let mut returned: Vec<_> = outcome.videos.iter().map(|video| video.id.as_str()).collect();
+            returned.sort();
+            assert_eq!(returned, vec!["v1", "v2", "v3"], "{}: returned videos", name);
+        }
+    }
+
+    #[test]
+    fn default_settings_use_supported_openai_response_format() {
+        let settings = AppSettings::default();
+        let output = batch_analysis_output();
+        let mut config = OpenAiProvider::default().config(&settings);
+        assert!(config.structured_output);
+
+        // 默认模型不支持 json_schema，退回 json_object
+        let format = openai_response_format(&config, &output).expect("default model supports json_object");
+        assert_eq!(format["type"], "json_object");
+        assert!(build_batch_analysis_prompt(&test_videos(), BatchAnalysisOptions {
+            custom_prompt: None,
+            context: &PromptContext::from_settings(&settings, "balanced"),
+            min_duration: None,
+            max_duration: None,
+        })
+        .contains("JSON"));
+
+        for (model, base_url, expected) in [
+            ("gpt-4o-mini", OPENAI_DEFAULT_BASE_URL, Some("json_schema")),
+            ("gpt-4.1", OPENAI_DEFAULT_BASE_URL, Some("json_schema")),
+            ("gpt-4-turbo", OPENAI_DEFAULT_BASE_URL, Some("json_object")),
+            ("gpt-4", OPENAI_DEFAULT_BASE_URL, None),
+            ("llama3", "http://localhost:11434/v1", Some("json_schema")),
+        ] {
+            config.model = model.to_string();
+            config.base_url = base_url.to_string();
+            let format = openai_response_format(&config, &output);
+            assert_eq!(format.as_ref().and_then(|f| f["type"].as_str()), expected, "{}", model);
+        }
+    }
+
+    #[test]
+    fn unverified_policy_marks_warned_videos() {
+        let mut videos = test_videos();
+        videos[0].analysis_status = Some(ANALYSIS_VERIFIED.to_string());
+        videos[0].age_appropriate = Some(true);
+        videos[0].ai_score = Some(0.9);
+        videos[1].mark_unverified();
+
+        let warned = filter_videos_by_mode(videos.clone(), "balanced", "warn");
+        let warnings: Vec<_> = warned.iter().map(|video| (video.id.as_str(), video.warning.is_some())).collect();
+        assert_eq!(warnings, vec![("v1", false), ("v2", true), ("v3", true)]);
+
+        let allowed = filter_videos_by_mode(videos.clone(), "balanced", "allow");
+        assert_eq!(allowed.len(), 3);
+        assert!(allowed.iter().all(|video| video.warning.is_none()));
+
+        let blocked = filter_videos_by_mode(videos, "balanced", "block");
+        assert_eq!(blocked.iter().map(|video| video.id.as_str()).collect::<Vec<_>>(), vec!["v1"]);
+    }
+
+    #[test]
+    fn parent_lockout_escalates_until_success() {
+        let mut lock = ParentLock::default();
+        for _ in 1..PARENT_PIN_MAX_ATTEMPTS {
+            assert_eq!(lock.record_failure(), 0);
+        }
+        assert_eq!(lock.lockout_remaining(), 0);
+
+        // 达到上限后每次输错锁定时间翻倍
+        assert_eq!(lock.record_failure(), PARENT_PIN_LOCKOUT_SECS);
+        assert!(lock.lockout_remaining() > 0);
+        assert_eq!(lock.record_failure(), PARENT_PIN_LOCKOUT_SECS * 2);
+        assert_eq!(lock.record_failure(), PARENT_PIN_LOCKOUT_SECS * 4);
+
+        // 最长不超过上限，次数很大时也不会溢出
+        for _ in 0..100 {
+            lock.record_failure();
+        }
+        assert_eq!(lock.record_failure(), PARENT_PIN_MAX_LOCKOUT_SECS);
+
+        // 验证成功后清零，重新从第一次锁定开始计算
+        let token = lock.record_success();
+        assert_eq!(lock.lockout_remaining(), 0);
+        assert!(lock.session_valid(Some(&token)));
+        assert!(!lock.session_valid(Some("wrong")));
+        for _ in 1..PARENT_PIN_MAX_ATTEMPTS {
+            assert_eq!(lock.record_failure(), 0);
+        }
+        assert_eq!(lock.record_failure(), PARENT_PIN_LOCKOUT_SECS);
+    }
+
+    #[test]
+    fn parse_batch_analysis_response_rejects_unparseable_output() {
+        assert!(parse_batch_analysis_response(&test_videos(), "no json here", None, None).is_err());
+        assert!(parse_batch_analysis_response(&test_videos(), r#"{"items": []}"#, None, None).is_err());
+    }
+
+    // 旧版本（没有 schema_version 表、视频以 video_data JSON 保存）的数据库
+    async fn seed_v1_database(pool: &SqlitePool) {
+        for step in MIGRATIONS[0].steps {
+            if let MigrationStep::Sql(sql) = step {
+                sqlx::query(sql).execute(pool).await.unwrap();
+            }
+        }
+        sqlx::query(
+            "INSERT INTO cached_videos (id, query, platform, video_data, cached_at, expires_at) VALUES
+             ('a', 'dinosaurs', 'youtube', '{\"id\":\"a\",\"title\":\"Dino A\",\"duration\":300,\"ai_score\":0.9}', '2024-01-01T00:00:00Z', '2999-01-01T00:00:00Z')"
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO favorites (video_id, user_notes, created_at, video_data) VALUES
+             ('a', NULL, '2024-01-01T00:00:00Z', '{\"id\":\"a\",\"title\":\"Old title\"}'),
+             ('b', 'likes it', '2024-01-02T00:00:00Z', '{\"id\":\"b\",\"title\":\"Fav B\",\"safety_score\":0.95}')"
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO search_history (query, platform, filter_mode, results_count, created_at)
+             VALUES ('dinosaurs', 'youtube', 'balanced', 1, '2024-01-01T00:00:00Z')"
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn table_snapshot(pool: &SqlitePool) -> Vec<(String, i64)> {
+        let mut snapshot = Vec::new();
+        for table in ["schema_version", "videos", "favorites", "search_history", "profiles", "cached_videos"] {
+            let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table))
+                .fetch_one(pool)
+                .await
+                .unwrap();
+            snapshot.push((table.to_string(), count));
+        }
+        snapshot
+    }
+
+    fn backup_files(dir: &Path) -> Vec<String> {
+        std::fs::read_dir(dir.join("backups"))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn run_migrations_upgrades_v1_database() {
+        let dir = std::env::temp_dir().join(format!("kid-videos-migration-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("app.db");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(&format!("sqlite:{}?mode=rwc", db_path.display()))
+            .await
+            .unwrap();
+
+        seed_v1_database(&pool).await;
+        run_migrations(&pool, &db_path).await.unwrap();
+
+        let (version,): (i64,) = sqlx::query_as("SELECT MAX(version) FROM schema_version")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.last().unwrap().version);
+
+        // video_data 已拆解到 videos 表，缓存中的副本优先于收藏中的副本，评分归入默认档案
+        let videos: Vec<(String, String, Option<f64>)> = sqlx::query_as(
+            "SELECT v.id, v.title, s.ai_score FROM videos v
+             LEFT JOIN profile_video_scores s ON s.video_id = v.id AND s.profile_id = 1
+             ORDER BY v.id"
+        )
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            videos,
+            vec![
+                ("a".to_string(), "Dino A".to_string(), Some(0.9)),
+                ("b".to_string(), "Fav B".to_string(), None),
+            ]
+        );
+
+        // 已有的收藏和搜索历史归入默认档案
+        let favorites: Vec<(i64, String, Option<String>)> =
+            sqlx::query_as("SELECT profile_id, video_id, user_notes FROM favorites ORDER BY video_id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            favorites,
+            vec![(1, "a".to_string(), None), (1, "b".to_string(), Some("likes it".to_string()))]
+        );
+        let (history_profile,): (i64,) = sqlx::query_as("SELECT profile_id FROM search_history")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(history_profile, 1);
+        let (active_profile,): (i64,) = sqlx::query_as("SELECT id FROM profiles WHERE is_active = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(active_profile, 1);
+
+        let backups = backup_files(&dir);
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].starts_with("app-v0-"), "unexpected backup file {}", backups[0]);
+
+        // 再次运行不做任何修改，也不再备份
+        let before = table_snapshot(&pool).await;
+        run_migrations(&pool, &db_path).await.unwrap();
+        assert_eq!(table_snapshot(&pool).await, before);
+        assert_eq!(backup_files(&dir), backups);
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    struct FixedClock(chrono::DateTime<chrono::Utc>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> chrono::DateTime<chrono::Utc> {
+            self.0
+        }
+    }
+
+    fn fixed_clock(rfc3339: &str) -> FixedClock {
+        FixedClock(chrono::DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&chrono::Utc))
+    }
+
+    fn schedule(timezone: &str, windows: &[(&[u32], &str, &str)]) -> ViewingSchedule {
+        ViewingSchedule {
+            timezone: Some(timezone.to_string()),
+            windows: windows
+                .iter()
+                .map(|(weekdays, start, end)| ViewingWindow {
+                    weekdays: weekdays.to_vec(),
+                    start: start.to_string(),
+                    end: end.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn allowed_at(schedule: &ViewingSchedule, now: &str) -> bool {
+        evaluate_schedule(1, schedule.clone(), None, &fixed_clock(now)).unwrap().allowed
+    }
+
+    #[test]
+    fn schedule_window_boundaries() {
+        // 2024-01-01 是周一
+        let weekdays = schedule("UTC", &[(&[1, 2, 3, 4, 5], "08:00", "18:00")]);
+        assert!(allowed_at(&weekdays, "2024-01-01T10:00:00Z"));
+        assert!(allowed_at(&weekdays, "2024-01-01T08:00:00Z"));
+        assert!(!allowed_at(&weekdays, "2024-01-01T07:59:00Z"));
+        assert!(!allowed_at(&weekdays, "2024-01-01T18:00:00Z"));
+        assert!(!allowed_at(&weekdays, "2024-01-06T10:00:00Z"));
+
+        assert!(allowed_at(&ViewingSchedule::default(), "2024-01-01T03:00:00Z"));
+    }
+
+    #[test]
+    fn schedule_weekday_boundaries() {
+        let weekend = schedule("UTC", &[(&[6, 7], "00:00", "24:00")]);
+        assert!(!allowed_at(&weekend, "2024-01-05T23:59:00Z")); // 周五
+        assert!(allowed_at(&weekend, "2024-01-06T00:00:00Z")); // 周六
+        assert!(allowed_at(&weekend, "2024-01-07T23:59:00Z")); // 周日
+        assert!(!allowed_at(&weekend, "2024-01-08T00:00:00Z")); // 周一
+    }
+
+    #[test]
+    fn schedule_end_of_day() {
+        assert_eq!(parse_clock_minutes("24:00").unwrap(), 24 * 60);
+        assert!(parse_clock_minutes("24:01").is_err());
+        assert!(parse_clock_minutes("12:60").is_err());
+
+        let evening = schedule("UTC", &[(&[1], "20:00", "24:00")]);
+        assert!(evening.validate().is_ok());
+        assert!(allowed_at(&evening, "2024-01-01T23:59:00Z"));
+        assert!(!allowed_at(&evening, "2024-01-02T00:00:00Z"));
+    }
+
+    #[test]
+    fn schedule_uses_profile_timezone() {
+        // 上海为 UTC+8：UTC 周一 00:30 是当地周一 08:30
+        let shanghai = schedule("Asia/Shanghai", &[(&[1], "08:00", "09:00")]);
+        assert!(allowed_at(&shanghai, "2024-01-01T00:30:00Z"));
+        assert!(!allowed_at(&shanghai, "2024-01-01T08:30:00Z"));
+
+        // 洛杉矶冬令时为 UTC-8：UTC 周一 02:30 仍是当地周日 18:30
+        let los_angeles = schedule("America/Los_Angeles", &[(&[7], "18:00", "20:00")]);
+        assert!(allowed_at(&los_angeles, "2024-01-08T02:30:00Z"));
+        assert!(!allowed_at(&los_angeles, "2024-01-08T18:30:00Z"));
+
+        // 夏令时为 UTC-7
+        let summer = schedule("America/Los_Angeles", &[(&[1], "09:00", "10:00")]);
+        assert!(allowed_at(&summer, "2024-07-01T16:30:00Z"));
+        assert!(!allowed_at(&summer, "2024-07-01T15:30:00Z"));
+
+        assert!(schedule("Mars/Olympus", &[]).validate().is_err());
+    }
+
+    #[test]
+    fn schedule_grace_extension_expires() {
+        let closed = schedule("UTC", &[(&[1], "08:00", "09:00")]);
+        let clock = fixed_clock("2024-01-01T20:00:00Z");
+
+        let extended = evaluate_schedule(1, closed.clone(), Some("2024-01-01T20:10:00+00:00".to_string()), &clock).unwrap();
+        assert!(!extended.in_window);
+        assert!(extended.allowed);
+        assert!(extended.grace_until.is_some());
+
+        let expired = evaluate_schedule(1, closed.clone(), Some("2024-01-01T19:59:00+00:00".to_string()), &clock).unwrap();
+        assert!(!expired.allowed);
+        assert!(expired.grace_until.is_none());
+
+        let invalid = evaluate_schedule(1, closed, Some("not a date".to_string()), &clock).unwrap();
+        assert!(!invalid.allowed);
+    }
+
+    #[test]
+    fn screen_time_date_follows_schedule_timezone() {
+        let clock = fixed_clock("2024-03-10T06:30:00Z");
+        let date_in = |timezone: &str| schedule(timezone, &[]).local_date(clock.now()).unwrap();
+
+        assert_eq!(date_in("Asia/Shanghai"), "2024-03-10");
+        assert_eq!(date_in("America/Los_Angeles"), "2024-03-09");
+        assert_eq!(date_in("UTC"), "2024-03-10");
+
+        // 未设置时区时使用系统时区
+        let system = ViewingSchedule { timezone: None, windows: vec![] };
+        let expected = clock.0.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string();
+        assert_eq!(system.local_date(clock.now()).unwrap(), expected);
+
+        assert!(schedule("Mars/Olympus", &[]).local_date(clock.now()).is_err());
+    }
+
+    #[tokio::test]
+    async fn analyze_batch_cached_keeps_search_order() {
+        let pool = migrated_pool("analysis-cache").await;
+        let provider = StubProvider::default();
+        let config = provider.config(&AppSettings::default());
+        let context = PromptContext::from_settings(&AppSettings::default(), "balanced");
+        let options = stub_options(&context);
+        let videos = numbered_videos(4);
+
+        // v2 缓存为通过，v3 缓存为拒绝；v1、v4 需要重新分析
+        let key = AnalysisCacheKey::new(provider.id(), &config, options, 7);
+        let cached: Vec<_> = [("v2", true), ("v3", false)]
+            .into_iter()
+            .map(|(id, approved)| VideoAnalysisRecord {
+                video_id: id.to_string(),
+                ai_score: if approved { 0.9 } else { 0.2 },
+                education_score: 0.8,
+                safety_score: 0.9,
+                approved,
+                reasoning: "cached".to_string(),
+                recommended_age: None,
+                age_fit_score: None,
+                quality_score: None,
+            })
+            .collect();
+        store_cached_analyses(&pool, &key, &cached).await.unwrap();
+
+        let outcome = analyze_batch_cached(&pool, &provider, &Client::new(), &config, &videos, options, 7)
+            .await
+            .unwrap();
+
+        assert_eq!(*provider.calls.lock().unwrap(), vec![vec!["v1".to_string(), "v4".to_string()]]);
+        assert_eq!(video_ids(&outcome.videos), vec!["v1", "v2", "v4"]);
+        let record_ids: Vec<_> = outcome.records.iter().map(|record| record.video_id.as_str()).collect();
+        assert_eq!(record_ids, vec!["v1", "v2", "v3", "v4"]);
+        assert_eq!(outcome.videos[1].ai_reasoning.as_deref(), Some("cached"));
+    }
+
+    #[tokio::test]
+    async fn provider_registry_dispatches_to_registered_provider() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Arc::new(StubProvider::default()));
+
+        assert_eq!(registry.resolve("stub").id(), "stub");
+        assert_eq!(registry.resolve("anthropic").id(), "anthropic");
+        assert_eq!(registry.resolve("unknown").id(), DEFAULT_AI_PROVIDER);
+
+        // 默认的 analyze_video / analyze_batch 通过 trait 调用到注册的 complete
+        let provider = registry.resolve("stub");
+        let settings = AppSettings::default();
+        let config = provider.config(&settings);
+        assert!(provider.is_configured(&config));
+
+        let client = Client::new();
+        let context = PromptContext::from_settings(&settings, "balanced");
+        let single = provider
+            .analyze_video(&client, &config, "Video v1", "description", None, &context)
+            .await
+            .unwrap();
+        assert!(single.age_appropriate);
+        assert_eq!(single.reasoning, "stub");
+
+        let videos = test_videos();
+        let batch = provider.analyze_batch(&client, &config, &videos, stub_options(&context)).await.unwrap();
+        assert_eq!(video_ids(&batch.videos), vec!["v1", "v2", "v3"]);
+        assert!(batch.videos.iter().all(|video| video.is_verified()));
+    }
+
+    #[tokio::test]
+    async fn process_videos_in_chunks_keeps_order_across_concurrent_chunks() {
+        let provider = StubProvider::default();
+        let config = provider.config(&AppSettings::default());
+        let context = PromptContext::from_settings(&AppSettings::default(), "balanced");
+        let videos = numbered_videos(5);
+
+        // 第一个块最晚完成，其余块先返回
+        let outcome = process_videos_in_chunks(&provider, &Client::new(), &config, &videos, 2, stub_options(&context))
+            .await
+            .unwrap();
+
+        assert_eq!(provider.calls.lock().unwrap().len(), 3);
+        assert_eq!(video_ids(&outcome.videos), vec!["v1", "v2", "v3", "v4", "v5"]);
+        let record_ids: Vec<_> = outcome.records.iter().map(|record| record.video_id.as_str()).collect();
+        assert_eq!(record_ids, vec!["v1", "v2", "v3", "v4", "v5"]);
+        assert!(outcome.failed_chunks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn process_videos_in_chunks_keeps_other_chunks_when_one_fails() {
+        let provider = StubProvider {
+            failing_video: Some("v3"),
+            ..Default::default()
+        };
+        let config = provider.config(&AppSettings::default());
+        let context = PromptContext::from_settings(&AppSettings::default(), "balanced");
+        let videos = numbered_videos(5);
+
+        let outcome = process_videos_in_chunks(&provider, &Client::new(), &config, &videos, 2, stub_options(&context))
+            .await
+            .unwrap();
+
+        assert_eq!(video_ids(&outcome.videos), vec!["v1", "v2", "v3", "v4", "v5"]);
+        let verified: Vec<_> = outcome.videos.iter().map(|video| video.is_verified()).collect();
+        assert_eq!(verified, vec![true, true, false, false, true]);
+
+        assert_eq!(outcome.failed_chunks.len(), 1);
+        assert_eq!(outcome.failed_chunks[0].chunk_index, 1);
+        assert_eq!(outcome.failed_chunks[0].video_ids, vec!["v3", "v4"]);
+        assert!(outcome.failed_chunks[0].error.contains("stub provider failure"));
+    }
+
+    fn locale(search_language: &str) -> SearchLocale {
+        SearchLocale::from_settings(&AppSettings {
+            search_language: search_language.to_string(),
+            search_region: " tw ".to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn search_locale_page_tokens_round_trip() {
+        let single = locale("en");
+        assert_eq!(single.languages, vec!["en"]);
+        assert_eq!(single.region, "TW");
+        assert_eq!(single.decode_page_token(None), None);
+        assert_eq!(single.decode_page_token(Some("")), None);
+
+        // 单一语言直接使用YouTube的令牌
+        let tokens = single.decode_page_token(Some("CAUQAA")).unwrap();
+        assert_eq!(tokens, HashMap::from([("en".to_string(), "CAUQAA".to_string())]));
+        assert_eq!(single.encode_page_token(&tokens).as_deref(), Some("CAUQAA"));
+        assert_eq!(single.encode_page_token(&HashMap::new()), None);
+
+        // 多语言时编码为JSON，只剩一种语言有下一页时也能还原
+        let both = locale("both");
+        assert_eq!(both.languages, vec!["zh", "en"]);
+        for tokens in [
+            HashMap::from([("zh".to_string(), "zh-page".to_string()), ("en".to_string(), "en-page".to_string())]),
+            HashMap::from([("en".to_string(), "en-page".to_string())]),
+        ] {
+            let encoded = both.encode_page_token(&tokens).unwrap();
+            assert_eq!(both.decode_page_token(Some(&encoded)), Some(tokens));
+        }
+        assert_eq!(both.decode_page_token(Some("CAUQAA")), None);
+    }
+
+    #[test]
+    fn merge_locale_pages_interleaves_and_dedupes() {
+        let page = |ids: &[&str]| -> Vec<Video> {
+            ids.iter()
+                .map(|id| Video {
+                    id: id.to_string(),
+                    ..Default::default()
+                })
+                .collect()
+        };
+
+        let merged = merge_locale_pages(vec![page(&["a", "b", "c", "d"]), page(&["x", "b", "a", "y"])]);
+        assert_eq!(video_ids(&merged), vec!["a", "x", "b", "c", "d", "y"]);
+
+        assert_eq!(video_ids(&merge_locale_pages(vec![page(&["a", "b"]), page(&[])])), vec!["a", "b"]);
+        assert!(merge_locale_pages(vec![]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_cache_is_keyed_by_profile_query_platform_and_mode() {
+        let pool = migrated_pool("search-cache").await;
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query("INSERT INTO profiles (id, name, is_active, created_at, updated_at) VALUES (2, 'Second', 0, ?, ?)")
+            .bind(&now)
+            .bind(&now)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // 测试池只有一个连接，每次写入单独取用，避免与读取互相等待
+        let videos = numbered_videos(4);
+        for video in &videos {
+            insert_video_if_missing(&mut pool.acquire().await.unwrap(), video).await.unwrap();
+        }
+
+        let query = normalize_search_query("  Dinosaur\tSONGS  ");
+        assert_eq!(query, "dinosaur songs");
+        let key = SearchCacheKey { profile_id: 1, query: &query, platform: "youtube", filter_mode: "balanced" };
+        let expires_at = cache_expires_at(1);
+        save_search_cache(&mut pool.acquire().await.unwrap(), &key, &videos[..2], None, Some("page-2"), &now, &expires_at).await.unwrap();
+
+        let (cached, next) = load_cached_search(&pool, &key).await.unwrap();
+        assert_eq!(video_ids(&cached), vec!["v1", "v2"]);
+        assert_eq!(next.as_deref(), Some("page-2"));
+
+        // 任一键字段不同都不命中
+        for other in [
+            SearchCacheKey { profile_id: 2, ..key },
+            SearchCacheKey { query: "dinosaur", ..key },
+            SearchCacheKey { platform: "youtube_kids", ..key },
+            SearchCacheKey { filter_mode: "strict", ..key },
+        ] {
+            assert!(load_cached_search(&pool, &other).await.unwrap().0.is_empty());
+        }
+
+        // 只有紧接在缓存之后的页才追加
+        save_search_cache(&mut pool.acquire().await.unwrap(), &key, &videos[3..], Some("page-9"), None, &now, &expires_at).await.unwrap();
+        save_search_cache(&mut pool.acquire().await.unwrap(), &key, &videos[2..3], Some("page-2"), Some("page-3"), &now, &expires_at).await.unwrap();
+        let (cached, next) = load_cached_search(&pool, &key).await.unwrap();
+        assert_eq!(video_ids(&cached), vec!["v1", "v2", "v3"]);
+        assert_eq!(next.as_deref(), Some("page-3"));
+
+        // 重新保存第一页会替换之前的缓存
+        save_search_cache(&mut pool.acquire().await.unwrap(), &key, &videos[3..], None, None, &now, &expires_at).await.unwrap();
+        let (cached, next) = load_cached_search(&pool, &key).await.unwrap();
+        assert_eq!(video_ids(&cached), vec!["v4"]);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn quota_date_rolls_over_at_pacific_midnight() {
+        // 冬令时为 UTC-8
+        assert_eq!(quota_date(&fixed_clock("2024-01-02T07:59:59Z")), "2024-01-01");
+        assert_eq!(quota_date(&fixed_clock("2024-01-02T08:00:00Z")), "2024-01-02");
+        // 夏令时为 UTC-7
+        assert_eq!(quota_date(&fixed_clock("2024-07-02T06:59:59Z")), "2024-07-01");
+        assert_eq!(quota_date(&fixed_clock("2024-07-02T07:00:00Z")), "2024-07-02");
+    }
+
+    #[tokio::test]
+    async fn youtube_quota_budget_refuses_once_used_up() {
+        let pool = migrated_pool("quota-budget").await;
+        let before_reset = fixed_clock("2024-01-02T07:30:00Z");
+        let after_reset = fixed_clock("2024-01-02T08:30:00Z");
+        let daily_quota = 201;
+
+        record_quota_usage(&pool, &before_reset, "search.list", SEARCH_LIST_QUOTA_COST).await.unwrap();
+        record_quota_usage(&pool, &before_reset, "search.list", SEARCH_LIST_QUOTA_COST).await.unwrap();
+        assert_eq!(quota_used_today(&pool, &before_reset).await.unwrap(), 200);
+        assert!(!youtube_quota_exhausted(&pool, &before_reset, daily_quota).await);
+
+        record_quota_usage(&pool, &before_reset, "videos.list", VIDEOS_LIST_QUOTA_COST).await.unwrap();
+        assert!(youtube_quota_exhausted(&pool, &before_reset, daily_quota).await);
+
+        // 太平洋时间午夜后配额重新计算
+        assert_eq!(quota_used_today(&pool, &after_reset).await.unwrap(), 0);
+        assert!(!youtube_quota_exhausted(&pool, &after_reset, daily_quota).await);
+    }
+}